@@ -0,0 +1,261 @@
+//! 盤面操作と解法のベンチマーク
+//!
+//! `cargo bench` で実行する。結果は `target/bench/<name>.csv` に保存され、次の実行ではそれと比較する。
+//! - `cargo bench -- <filter>` 名前にfilterを含むものだけ実行する
+//! - `cargo bench -- --save-baseline <name>` 結果を保存する名前（デフォルトは `base`）
+//! - `cargo bench -- --baseline <name>` 比較する結果の名前（デフォルトは保存する名前と同じ）
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use ahc042::board::*;
+use ahc042::portfolio::solve_portfolio;
+use ahc042::solver::{fall_oni_and_reset, find_oni, Config, Solver, Strategy};
+
+/// 1サンプルの目安の時間
+const SAMPLE_TIME: Duration = Duration::from_millis(20);
+const NUM_SAMPLES: usize = 50;
+/// 1回が遅い処理でも最低限取るサンプル数
+const MIN_SAMPLES: usize = 3;
+const MAX_TIME: Duration = Duration::from_secs(5);
+
+struct Bench {
+    filter: Option<String>,
+    save: String,
+    baseline: HashMap<String, f64>,
+    results: Vec<(String, f64)>,
+}
+
+impl Bench {
+    fn from_args() -> Self {
+        let mut filter = None;
+        let mut save = "base".to_owned();
+        let mut baseline = None;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--save-baseline" => save = args.next().expect("--save-baseline <name>"),
+                "--baseline" => baseline = Some(args.next().expect("--baseline <name>")),
+                // cargo bench が渡す引数
+                "--bench" => (),
+                _ if arg.starts_with('-') => (),
+                _ => filter = Some(arg),
+            }
+        }
+        let baseline = read_results(&result_path(baseline.as_ref().unwrap_or(&save)));
+        Self {
+            filter,
+            save,
+            baseline,
+            results: Vec::new(),
+        }
+    }
+
+    /// setupで作った状態に対してroutineを繰り返し、1回あたりの時間の中央値を表示する
+    ///
+    /// setupはサンプルごとに1回呼ばれ、時間には含めない。
+    fn run<S, R>(
+        &mut self,
+        name: &str,
+        mut setup: impl FnMut() -> S,
+        mut routine: impl FnMut(&mut S) -> R,
+    ) {
+        if self
+            .filter
+            .as_ref()
+            .map_or(false, |filter| !name.contains(filter))
+        {
+            return;
+        }
+
+        // 1サンプルがSAMPLE_TIME程度になる繰り返し回数を見積もる
+        let mut iters = 1;
+        loop {
+            let mut state = setup();
+            let start = Instant::now();
+            for _ in 0..iters {
+                black_box(routine(&mut state));
+            }
+            if start.elapsed() >= SAMPLE_TIME || iters >= 1 << 30 {
+                break;
+            }
+            iters *= 2;
+        }
+
+        let started = Instant::now();
+        let mut samples = Vec::new();
+        while samples.len() < NUM_SAMPLES
+            && (samples.len() < MIN_SAMPLES || started.elapsed() < MAX_TIME)
+        {
+            let mut state = setup();
+            let start = Instant::now();
+            for _ in 0..iters {
+                black_box(routine(&mut state));
+            }
+            samples.push(start.elapsed().as_nanos() as f64 / iters as f64);
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = samples[samples.len() / 2];
+
+        let change = match self.baseline.get(name) {
+            Some(&base) => format!("{:+.1}%", (median / base - 1.0) * 100.0),
+            None => "no baseline".to_owned(),
+        };
+        println!(
+            "{:<32} {:>14} [{} .. {}] ({})",
+            name,
+            format_ns(median),
+            format_ns(samples[0]),
+            format_ns(samples[samples.len() - 1]),
+            change
+        );
+        self.results.push((name.to_owned(), median));
+    }
+
+    fn save(&self) {
+        let path = result_path(&self.save);
+        let mut results = read_results(&path);
+        for (name, ns) in &self.results {
+            results.insert(name.clone(), *ns);
+        }
+        let mut names = results.keys().collect::<Vec<_>>();
+        names.sort();
+        let csv = names
+            .iter()
+            .map(|&name| format!("{},{}\n", name, results[name]))
+            .collect::<String>();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, format!("name,ns_per_iter\n{}", csv)).unwrap();
+        println!("saved to {}", path.display());
+    }
+}
+
+fn result_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("bench")
+        .join(format!("{}.csv", name))
+}
+
+fn read_results(path: &PathBuf) -> HashMap<String, f64> {
+    let Ok(csv) = std::fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+    csv.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (name, ns) = line.split_once(',')?;
+            Some((name.to_owned(), ns.parse().ok()?))
+        })
+        .collect()
+}
+
+fn format_ns(ns: f64) -> String {
+    if ns < 1e3 {
+        format!("{:.1} ns", ns)
+    } else if ns < 1e6 {
+        format!("{:.2} us", ns / 1e3)
+    } else if ns < 1e9 {
+        format!("{:.2} ms", ns / 1e6)
+    } else {
+        format!("{:.2} s", ns / 1e9)
+    }
+}
+
+fn read_inputs() -> Vec<BoardState> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("input");
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+        .iter()
+        .map(|path| parse_input(&std::fs::read_to_string(path).unwrap()).unwrap())
+        .collect()
+}
+
+fn main() {
+    let mut bench = Bench::from_args();
+    let inputs = read_inputs();
+    let board_state = &inputs[0];
+    let n = board_state.n;
+
+    // 操作の時間は盤面の中身によらないので、同じ盤面を動かし続ける
+    for dir in Direction::ALL {
+        bench.run(
+            &format!("apply/{}", dir),
+            || (board_state.clone(), 0),
+            |(board, index)| {
+                board.apply(&Operation { dir, index: *index });
+                *index = (*index + 1) % n;
+            },
+        );
+    }
+
+    // 盤面の半分の長さをまとめて動かす
+    for dir in Direction::ALL {
+        bench.run(
+            &format!("apply_macro/{}", dir),
+            || (board_state.clone(), 0),
+            |(board, index)| {
+                board.apply_macro(&MacroOp {
+                    dir,
+                    index: *index,
+                    count: n / 2,
+                });
+                *index = (*index + 1) % n;
+            },
+        );
+    }
+
+    bench.run(
+        "exists_oni_around_all_fuku",
+        || (),
+        |_| board_state.exists_oni_around_all_fuku(),
+    );
+
+    let oni = board_state.oni_positions();
+    bench.run(
+        "fall_oni_and_reset",
+        || 0,
+        |k| {
+            let (y, x) = oni[*k];
+            *k = (*k + 1) % oni.len();
+            fall_oni_and_reset(y, x, board_state)
+        },
+    );
+    bench.run("find_oni", || (), |_| find_oni(board_state));
+
+    // 入力100ケースを全て解く時間
+    for strategy in Strategy::ALL {
+        bench.run(
+            &format!("solve_all/{}", strategy),
+            || (),
+            |_| {
+                for board_state in &inputs {
+                    black_box(
+                        Solver::new(Config {
+                            strategy,
+                            ..Config::default()
+                        })
+                        .solve(board_state),
+                    );
+                }
+            },
+        );
+    }
+    bench.run(
+        "solve_all/portfolio",
+        || (),
+        |_| {
+            for board_state in &inputs {
+                black_box(solve_portfolio(board_state, None, 0));
+            }
+        },
+    );
+
+    bench.save();
+}
@@ -0,0 +1,32 @@
+//! 任意の盤面サイズと文字列で `generate_board_from_string` がパニックしないこと
+#![no_main]
+
+use ahc042::board::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, board_str)) = data.split_first() else {
+        return;
+    };
+    let Ok(board_str) = std::str::from_utf8(board_str) else {
+        return;
+    };
+    let Ok(board_state) = generate_board_from_string(size as usize, board_str) else {
+        return;
+    };
+    let count = |room| {
+        board_state
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&r| r == room)
+            .count()
+    };
+    assert_eq!(board_state.board.len(), board_state.n);
+    assert!(board_state
+        .board
+        .iter()
+        .all(|row| row.len() == board_state.n));
+    assert_eq!(count(RoomState::Oni), board_state.num_oni);
+    assert_eq!(count(RoomState::Fuku), board_state.num_fuku);
+});
@@ -0,0 +1,22 @@
+//! 任意の文字列で `tools::parse_input` がパニックせず、受理する入力は `ahc042::board::parse_input` と一致すること
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    let tools_input = tools::parse_input(s);
+    let board_state = ahc042::board::parse_input(s);
+    if let Ok(input) = tools_input {
+        // ツールは空白区切りのトークンで読むので、ツールが受理した入力を書き出し直したものは解法側も受理する
+        let board_state = ahc042::board::parse_input(&input.to_string()).unwrap();
+        assert_eq!(
+            format!("{}", input),
+            format!("{}\n{:?}", board_state.n, board_state)
+        );
+    } else if let Ok(board_state) = board_state {
+        panic!("only the solver accepted the input: {:?}", board_state);
+    }
+});
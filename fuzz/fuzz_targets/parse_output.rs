@@ -0,0 +1,27 @@
+//! 任意の出力で `tools::parse_output` がパニックせず、受理した出力は `compute_score_details` で採点できること
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, out)) = data.split_first() else {
+        return;
+    };
+    let Ok(out) = std::str::from_utf8(out) else {
+        return;
+    };
+    // 出力の検査は盤面の大きさしか見ないので、福と鬼が1体ずつの盤面を使う
+    let n = size as usize % 20 + 2;
+    let mut board = vec![".".repeat(n); n];
+    board[0].replace_range(0..2, "xo");
+    let input = tools::parse_input(&format!("{}\n{}\n", n, board.join("\n"))).unwrap();
+
+    let first = tools::parse_output(&input, out);
+    let all = tools::parse_output_all(&input, out);
+    assert_eq!(first.is_ok(), all.is_ok());
+    if let Ok(output) = first {
+        assert!(output.out.len() <= 4 * n * n);
+        let (_, err, _) = tools::compute_score_details(&input, &output.out);
+        assert!(err.is_empty(), "{}", err);
+    }
+});
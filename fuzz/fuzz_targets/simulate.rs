@@ -0,0 +1,96 @@
+//! 任意の盤面と操作列で `BoardState::apply` と `tools::compute_score_details` のシミュレーションが一致すること
+//! 連続する同じ操作をまとめた `BoardState::apply_macro` の結果も一致すること
+//!
+//! 1バイト目で盤面の大きさ、続く n * n バイトでマス、残りの1バイトずつで操作を決める。
+#![no_main]
+
+use ahc042::board::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&size, data)) = data.split_first() else {
+        return;
+    };
+    let n = size as usize % 20 + 1;
+    if data.len() < n * n {
+        return;
+    }
+    let (cells, ops) = data.split_at(n * n);
+    let board_str = cells
+        .chunks(n)
+        .map(|row| {
+            row.iter()
+                .map(|&c| ['.', 'x', 'o'][c as usize % 3])
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let input_str = format!("{}\n{}\n", n, board_str);
+    let input = tools::parse_input(&input_str).unwrap();
+    let mut board_state = parse_input(&input_str).unwrap();
+    let num_fuku = board_state.num_fuku;
+
+    let operations = ops
+        .iter()
+        .map(|&op| Operation {
+            dir: Direction::ALL[op as usize % 4],
+            index: op as usize / 4 % n,
+        })
+        .collect::<Vec<_>>();
+    let out = operations
+        .iter()
+        .map(|operation| {
+            let s = operation.to_string();
+            (s.chars().next().unwrap(), operation.index)
+        })
+        .collect::<Vec<_>>();
+
+    let (timeline, err) = tools::compute_score_timeline(&input, &out);
+    assert!(err.is_empty(), "{}", err);
+    assert_eq!(timeline.len(), operations.len() + 1);
+    for (t, operation) in operations.iter().enumerate() {
+        board_state.apply(operation);
+        let step = &timeline[t + 1];
+        let lost_fuku = num_fuku - board_state.num_fuku;
+        assert_eq!(step.oni, board_state.num_oni, "t = {}", t + 1);
+        assert_eq!(step.lost_fuku, lost_fuku, "t = {}", t + 1);
+        assert_eq!(
+            step.score,
+            compute_score(n, t + 1, board_state.num_oni, lost_fuku),
+            "t = {}",
+            t + 1
+        );
+    }
+
+    let (score, err, cs) = tools::compute_score_details(&input, &out);
+    assert!(err.is_empty(), "{}", err);
+    assert_eq!(
+        score,
+        compute_score(
+            n,
+            operations.len(),
+            board_state.num_oni,
+            num_fuku - board_state.num_fuku
+        )
+    );
+    let expected = cs
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert_eq!(format!("{:?}", board_state).trim_end(), expected);
+
+    // 同じ操作が続く部分をまとめて適用しても同じ盤面になる
+    let mut macro_ops: Vec<MacroOp> = Vec::new();
+    for operation in &operations {
+        match macro_ops.last_mut() {
+            Some(last) if last.operation() == *operation => last.count += 1,
+            _ => macro_ops.push(MacroOp::new(*operation, 1)),
+        }
+    }
+    let mut macro_board = parse_input(&input_str).unwrap();
+    for macro_op in &macro_ops {
+        macro_board.apply_macro(macro_op);
+    }
+    assert_eq!(macro_board, board_state);
+});
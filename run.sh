@@ -12,8 +12,11 @@ mkdir -p output
 for file in "./input"/*; do
     if [ -f $file ]; then
         filename=$(basename "$file")
-        ./target/debug/$bin_name < $file > output/$filename
+        ./target/debug/$bin_name "$@" < $file > output/$filename 2> output/${filename%.*}.log
     fi
 done
 
+# 入力ごとに採用された設定の集計
+grep -h "^config:" output/*.log | sort | uniq -c | sort -rn
+
 echo "Finish!"
@@ -0,0 +1,180 @@
+use crate::board::*;
+use crate::solver::nearest_escape;
+
+/// 鬼を落としたときに動いた福が他の鬼の逃げ道を塞ぐ関係のグラフ
+///
+/// 各鬼を `nearest_escape` の方向に落として盤面を戻さなかったとき、
+/// それまで落とせた鬼が福に囲まれて落とせなくなるなら辺 a -> b を張る。
+/// 鬼を1体ずつ落とした場合だけを見ているので、複数の操作の組み合わせは考慮しない。
+pub struct BlockingGraph {
+    /// 鬼の位置 (y, x)、行優先順
+    pub oni: Vec<(usize, usize)>,
+    /// edges[a] は鬼aを落とすと落とせなくなる鬼の番号
+    pub edges: Vec<Vec<usize>>,
+}
+
+impl BlockingGraph {
+    pub fn new(board_state: &BoardState) -> Self {
+        let n = board_state.n;
+        let oni = board_state.oni_positions();
+        let mut edges = vec![Vec::new(); oni.len()];
+        for (a, &(y, x)) in oni.iter().enumerate() {
+            let Some((dir, index, count)) = nearest_escape(y, x, board_state) else {
+                continue;
+            };
+            let mut after_board = board_state.clone();
+            after_board.apply_macro(&MacroOp { dir, index, count });
+            for (b, &(by, bx)) in oni.iter().enumerate() {
+                if a == b || board_state.escape_directions(by, bx).is_empty() {
+                    continue;
+                }
+                // 動かした行（列）にいた鬼は一緒に動いている
+                let (by, bx) = match dir {
+                    Direction::Left if by == index => (by, bx.wrapping_sub(count)),
+                    Direction::Right if by == index => (by, bx + count),
+                    Direction::Up if bx == index => (by.wrapping_sub(count), bx),
+                    Direction::Down if bx == index => (by + count, bx),
+                    _ => (by, bx),
+                };
+                if by >= n || bx >= n {
+                    continue;
+                }
+                if after_board.escape_directions(by, bx).is_empty() {
+                    edges[a].push(b);
+                }
+            }
+        }
+
+        Self { oni, edges }
+    }
+
+    /// 強連結成分を返す（Tarjan法）
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            edges: &'a Vec<Vec<usize>>,
+            order: Vec<Option<usize>>,
+            low: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            counter: usize,
+            components: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn visit(&mut self, v: usize) {
+                self.order[v] = Some(self.counter);
+                self.low[v] = self.counter;
+                self.counter += 1;
+                self.stack.push(v);
+                self.on_stack[v] = true;
+                for &w in &self.edges[v] {
+                    match self.order[w] {
+                        None => {
+                            self.visit(w);
+                            self.low[v] = self.low[v].min(self.low[w]);
+                        }
+                        Some(order) if self.on_stack[w] => {
+                            self.low[v] = self.low[v].min(order);
+                        }
+                        _ => (),
+                    }
+                }
+                if Some(self.low[v]) == self.order[v] {
+                    let mut component = Vec::new();
+                    loop {
+                        let w = self.stack.pop().unwrap();
+                        self.on_stack[w] = false;
+                        component.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let m = self.oni.len();
+        let mut tarjan = Tarjan {
+            edges: &self.edges,
+            order: vec![None; m],
+            low: vec![0; m],
+            on_stack: vec![false; m],
+            stack: Vec::new(),
+            counter: 0,
+            components: Vec::new(),
+        };
+        for v in 0..m {
+            if tarjan.order[v].is_none() {
+                tarjan.visit(v);
+            }
+        }
+        tarjan.components
+    }
+
+    /// 盤面を戻さずに落とし続けられる順番を返す
+    ///
+    /// 辺 a -> b があれば b を先に落とす必要がある。閉路があるときはNone。
+    pub fn removal_order(&self) -> Option<Vec<usize>> {
+        let m = self.oni.len();
+        let mut out_degree = self.edges.iter().map(|e| e.len()).collect::<Vec<_>>();
+        let mut blocked_by = vec![Vec::new(); m];
+        for (a, e) in self.edges.iter().enumerate() {
+            for &b in e {
+                blocked_by[b].push(a);
+            }
+        }
+        let mut removed = vec![false; m];
+        let mut order = Vec::new();
+        while order.len() < m {
+            let v = (0..m).find(|&v| !removed[v] && out_degree[v] == 0)?;
+            removed[v] = true;
+            order.push(v);
+            for &a in &blocked_by[v] {
+                out_degree[a] -= 1;
+            }
+        }
+        Some(order)
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_graph() {
+        // (0, 3) を右に落とすと (0, 0) の福が (0, 1) に来て (1, 1) の鬼の逃げ道を塞ぐ
+        let board_state = generate_board_from_string(
+            4,
+            r"
+            o..x
+            oxo.
+            ....
+            .o..
+        ",
+        )
+        .unwrap();
+
+        let graph = BlockingGraph::new(&board_state);
+
+        assert_eq!(graph.oni, vec![(0, 3), (1, 1)]);
+        assert_eq!(graph.edges, vec![vec![1], vec![]]);
+        assert_eq!(graph.strongly_connected_components().len(), 2);
+        assert_eq!(graph.removal_order(), Some(vec![1, 0]));
+    }
+
+    #[test]
+    fn test_blocking_graph_cycle() {
+        let graph = BlockingGraph {
+            oni: vec![(0, 0), (1, 1), (2, 2)],
+            edges: vec![vec![1], vec![0], vec![0]],
+        };
+
+        let mut components = graph.strongly_connected_components();
+        components.sort();
+        assert_eq!(components, vec![vec![0, 1], vec![2]]);
+        assert_eq!(graph.removal_order(), None);
+    }
+}
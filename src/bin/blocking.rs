@@ -0,0 +1,58 @@
+//! 鬼同士の逃げ道を塞ぐ関係を解析して表示する
+use ahc042::analysis::BlockingGraph;
+use ahc042::board::*;
+
+fn main() {
+    if std::env::args().len() != 2 {
+        eprintln!("Usage: {} <input>", std::env::args().next().unwrap());
+        std::process::exit(1);
+    }
+    let in_file = std::env::args().nth(1).unwrap();
+    let input = std::fs::read_to_string(&in_file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", in_file);
+        std::process::exit(1)
+    });
+    let board_state = parse_input(&input).unwrap_or_else(|err| {
+        eprintln!("invalid input: {}: {}", in_file, err);
+        std::process::exit(1)
+    });
+    let graph = BlockingGraph::new(&board_state);
+    let pos = |v: usize| format!("({}, {})", graph.oni[v].0, graph.oni[v].1);
+
+    println!("oni: {}", graph.oni.len());
+    println!(
+        "edges: {}, oni that block others: {}",
+        graph.edges.iter().map(|e| e.len()).sum::<usize>(),
+        graph.edges.iter().filter(|e| !e.is_empty()).count()
+    );
+    for (a, e) in graph.edges.iter().enumerate() {
+        if !e.is_empty() {
+            let blocked = e.iter().map(|&b| pos(b)).collect::<Vec<_>>();
+            println!("{} -> {}", pos(a), blocked.join(", "));
+        }
+    }
+
+    let cycles = graph
+        .strongly_connected_components()
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .collect::<Vec<_>>();
+    println!(
+        "strongly connected components with cycles: {}",
+        cycles.len()
+    );
+    for c in &cycles {
+        println!(
+            "{}",
+            c.iter().map(|&v| pos(v)).collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    match graph.removal_order() {
+        Some(order) => println!(
+            "removal order: {}",
+            order.iter().map(|&v| pos(v)).collect::<Vec<_>>().join(" ")
+        ),
+        None => println!("removal order: none (some oni block each other)"),
+    }
+}
@@ -0,0 +1,395 @@
+//! 入力ファイルの盤面をターミナル上で手で動かして遊ぶためのプレイグラウンド
+//!
+//! 端末ではキーを押すとすぐに反映される。標準入力が端末でなければ1行ずつコマンドを読む。
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+use ahc042::board::*;
+
+const HELP: &str = "\
+keys:
+  arrows / h j k l  move the cursor
+  a / d             shift the cursor's row left / right
+  w / s             shift the cursor's column up / down
+  z / y             undo / redo
+  :                 enter a command (below)
+  ?                 show this help
+  q                 quit
+commands:
+  L i [k] / R i [k] / U i [k] / D i [k]  shift row (column) i k times (default 1)
+  z [k]       undo k operations (default 1)
+  y [k]       redo k operations (default 1)
+  w [file]    save the operations (default out.txt)
+  h           show this help
+  q           quit";
+
+struct Playground {
+    initial: BoardState,
+    board_state: BoardState,
+    /// 操作と、その操作で盤面から落ちた駒
+    history: Vec<(Operation, RoomState)>,
+    redo: Vec<Operation>,
+    cursor: (usize, usize),
+}
+
+/// 操作で盤面から落ちるマス
+fn fallen_cell(n: usize, operation: &Operation) -> (usize, usize) {
+    match operation.dir {
+        Direction::Left => (operation.index, 0),
+        Direction::Right => (operation.index, n - 1),
+        Direction::Up => (0, operation.index),
+        Direction::Down => (n - 1, operation.index),
+    }
+}
+
+impl Playground {
+    fn new(initial: BoardState) -> Self {
+        Self {
+            board_state: initial.clone(),
+            initial,
+            history: Vec::new(),
+            redo: Vec::new(),
+            cursor: (0, 0),
+        }
+    }
+
+    fn push(&mut self, operation: Operation) {
+        let (y, x) = fallen_cell(self.board_state.n, &operation);
+        let fallen = self.board_state.board[y][x];
+        self.board_state.apply(&operation);
+        self.history.push((operation, fallen));
+    }
+
+    fn apply(&mut self, operation: Operation) {
+        self.push(operation);
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some((operation, fallen)) = self.history.pop() else {
+            return false;
+        };
+        // 逆向きに動かすと反対側の空いたマスが落ち、落ちた駒のマスが空くのでそこに戻す
+        self.board_state.apply(&Operation {
+            dir: get_rev_dir(operation.dir),
+            index: operation.index,
+        });
+        let (y, x) = fallen_cell(self.board_state.n, &operation);
+        self.board_state.board[y][x] = fallen;
+        match fallen {
+            RoomState::Oni => self.board_state.num_oni += 1,
+            RoomState::Fuku => self.board_state.num_fuku += 1,
+            RoomState::Vacant => (),
+        }
+        self.redo.push(operation);
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(operation) = self.redo.pop() else {
+            return false;
+        };
+        self.push(operation);
+        true
+    }
+
+    /// cursorがあればそのマスを反転して表示する
+    fn render(&self, cursor: Option<(usize, usize)>) -> String {
+        let n = self.board_state.n;
+        let mut s = String::new();
+        let tens = (0..n)
+            .map(|j| {
+                if j % 10 == 0 {
+                    (j / 10).to_string()
+                } else {
+                    " ".to_owned()
+                }
+            })
+            .collect::<String>();
+        s += &format!("    {}\n", tens.trim_end());
+        s += &format!(
+            "    {}\n",
+            (0..n).map(|j| (j % 10).to_string()).collect::<String>()
+        );
+        for (i, row) in format!("{:?}", self.board_state).lines().enumerate() {
+            let row = match cursor {
+                Some((y, x)) if y == i => format!(
+                    "{}\x1b[7m{}\x1b[0m{}",
+                    &row[..x],
+                    &row[x..x + 1],
+                    &row[x + 1..]
+                ),
+                _ => row.to_owned(),
+            };
+            s += &format!("{:>3} {}\n", i, row);
+        }
+
+        let lost_fuku = self.initial.num_fuku - self.board_state.num_fuku;
+        s += &format!(
+            "T = {}, oni = {}, lost fuku = {}, score = {}\n",
+            self.history.len(),
+            self.board_state.num_oni,
+            lost_fuku,
+            compute_score(n, self.history.len(), self.board_state.num_oni, lost_fuku)
+        );
+
+        // 鬼ごとに福を落とさずに落とせる方向を表示する
+        let mut escapes = Vec::new();
+        for (i, j) in self.board_state.oni_positions() {
+            let dirs = self.board_state.escape_directions(i, j);
+            let dirs = if dirs.is_empty() {
+                "-".to_owned()
+            } else {
+                dirs.iter().map(|dir| dir.to_string()).collect()
+            };
+            escapes.push(format!("({:>2}, {:>2}) {:<4}", i, j, dirs));
+        }
+        for chunk in escapes.chunks(6) {
+            s += &format!("{}\n", chunk.join(" ").trim_end());
+        }
+        s
+    }
+
+    fn save(&self, file: &str) -> std::io::Result<()> {
+        let out = self
+            .history
+            .iter()
+            .map(|(operation, _)| format!("{}\n", operation))
+            .collect::<String>();
+        std::fs::write(file, out)
+    }
+
+    /// 1行のコマンドを実行し、表示するメッセージを返す
+    fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        let n = self.board_state.n;
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            None => Ok(None),
+            Some(d @ ("L" | "R" | "U" | "D")) => {
+                let dir = d.parse().unwrap();
+                match tokens.next().map(|i| i.parse::<usize>()) {
+                    Some(Ok(index)) if index < n => {
+                        // 上限 4N^2 を超える回数は意味がないので切り詰める
+                        let k = parse_count(tokens.next())?.min(4 * n * n);
+                        for _ in 0..k {
+                            self.apply(Operation { dir, index });
+                        }
+                        Ok(None)
+                    }
+                    _ => Err(format!("index must be in 0..{}", n)),
+                }
+            }
+            Some("z") => {
+                let k = parse_count(tokens.next())?.min(self.history.len());
+                for _ in 0..k {
+                    self.undo();
+                }
+                Ok(None)
+            }
+            Some("y") => {
+                let k = parse_count(tokens.next())?.min(self.redo.len());
+                for _ in 0..k {
+                    self.redo();
+                }
+                Ok(None)
+            }
+            Some("w") => {
+                let file = tokens.next().unwrap_or("out.txt");
+                self.save(file)
+                    .map(|_| {
+                        Some(format!(
+                            "saved {} operations to {}",
+                            self.history.len(),
+                            file
+                        ))
+                    })
+                    .map_err(|err| format!("failed to save {}: {}", file, err))
+            }
+            Some("h") => Ok(Some(HELP.to_owned())),
+            Some(command) => Err(format!("unknown command: {} (h for help)", command)),
+        }
+    }
+}
+
+fn parse_count(token: Option<&str>) -> Result<usize, String> {
+    match token {
+        Some(k) => k.parse().map_err(|_| format!("invalid count: {}", k)),
+        None => Ok(1),
+    }
+}
+
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// 端末をキー入力がすぐに読めるモードにし、dropで元に戻す
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Some(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+enum Key {
+    Char(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+fn read_key(stdin: &mut impl Read) -> Option<Key> {
+    let mut buf = [0];
+    stdin.read_exact(&mut buf).ok()?;
+    if buf[0] != 0x1b {
+        return Some(Key::Char(buf[0]));
+    }
+    // 矢印キーは ESC [ A-D。ESCだけが押されたときに次のキーを待たないよう、続きは0.1秒だけ待つ
+    stty(&["min", "0", "time", "1"]);
+    let mut seq = [0; 2];
+    let mut len = 0;
+    while len < seq.len() {
+        match stdin.read(&mut seq[len..]) {
+            Ok(k) if k > 0 => len += k,
+            _ => break,
+        }
+    }
+    stty(&["min", "1", "time", "0"]);
+    Some(match seq[..len] {
+        [b'[', b'A'] => Key::Up,
+        [b'[', b'B'] => Key::Down,
+        [b'[', b'C'] => Key::Right,
+        [b'[', b'D'] => Key::Left,
+        _ => Key::Char(0x1b),
+    })
+}
+
+/// キーを押すたびに盤面を描き直す
+fn run_keys(playground: &mut Playground, raw: RawMode) {
+    let n = playground.board_state.n;
+    let mut stdin = std::io::stdin().lock();
+    let mut message = HELP.to_owned();
+    let mut raw = Some(raw);
+    while raw.is_some() {
+        // rawモードでは改行で行頭に戻らない
+        let screen = format!(
+            "{}{}\n",
+            playground.render(Some(playground.cursor)),
+            message
+        );
+        print!("\x1b[2J\x1b[H{}", screen.replace('\n', "\r\n"));
+        std::io::stdout().flush().unwrap();
+        message.clear();
+        let Some(key) = read_key(&mut stdin) else {
+            break;
+        };
+        let (y, x) = playground.cursor;
+        match key {
+            Key::Up | Key::Char(b'k') => playground.cursor.0 = y.saturating_sub(1),
+            Key::Down | Key::Char(b'j') => playground.cursor.0 = (y + 1).min(n - 1),
+            Key::Left | Key::Char(b'h') => playground.cursor.1 = x.saturating_sub(1),
+            Key::Right | Key::Char(b'l') => playground.cursor.1 = (x + 1).min(n - 1),
+            Key::Char(c @ (b'a' | b'd' | b'w' | b's')) => {
+                let (dir, index) = match c {
+                    b'a' => (Direction::Left, y),
+                    b'd' => (Direction::Right, y),
+                    b'w' => (Direction::Up, x),
+                    _ => (Direction::Down, x),
+                };
+                playground.apply(Operation { dir, index });
+            }
+            Key::Char(b'z') => {
+                playground.undo();
+            }
+            Key::Char(b'y') => {
+                playground.redo();
+            }
+            Key::Char(b':') => {
+                // コマンドは普通の端末のモードで1行読む
+                drop(raw.take());
+                print!(":");
+                std::io::stdout().flush().unwrap();
+                let mut line = String::new();
+                if stdin.read_line(&mut line).unwrap_or(0) == 0 || line.trim() == "q" {
+                    break;
+                }
+                message = match playground.execute(&line) {
+                    Ok(msg) => msg.unwrap_or_default(),
+                    Err(err) => err,
+                };
+                raw = RawMode::enable();
+            }
+            Key::Char(b'?') => message = HELP.to_owned(),
+            // Ctrl-C, Ctrl-D
+            Key::Char(b'q' | 0x03 | 0x04) => break,
+            Key::Char(_) => (),
+        }
+    }
+}
+
+/// 1行ずつコマンドを読む
+fn run_lines(playground: &mut Playground) {
+    println!("{}", HELP);
+    print!("{}> ", playground.render(None));
+    std::io::stdout().flush().unwrap();
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap();
+        if line.trim() == "q" {
+            break;
+        }
+        match playground.execute(&line) {
+            Ok(Some(msg)) => println!("{}", msg),
+            Ok(None) => (),
+            Err(err) => println!("{}", err),
+        }
+        print!("{}> ", playground.render(None));
+        std::io::stdout().flush().unwrap();
+    }
+}
+
+fn main() {
+    if std::env::args().len() != 2 {
+        eprintln!("Usage: {} <input>", std::env::args().next().unwrap());
+        std::process::exit(1);
+    }
+    let in_file = std::env::args().nth(1).unwrap();
+    let input = std::fs::read_to_string(&in_file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", in_file);
+        std::process::exit(1)
+    });
+    let board_state = parse_input(&input).unwrap_or_else(|err| {
+        eprintln!("invalid input: {}: {}", in_file, err);
+        std::process::exit(1)
+    });
+    let mut playground = Playground::new(board_state);
+
+    let raw = if std::io::stdin().is_terminal() {
+        RawMode::enable()
+    } else {
+        None
+    };
+    match raw {
+        Some(raw) => run_keys(&mut playground, raw),
+        None => run_lines(&mut playground),
+    }
+}
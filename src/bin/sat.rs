@@ -0,0 +1,137 @@
+//! 「福を落とさずにT回以内の操作で全ての鬼を落とせるか」をSATに符号化する
+//!
+//! - `sat encode <input> <T>` DIMACS CNFを標準出力に書く
+//! - `sat decode <input> <T> <solver output>` SATソルバの出力を操作列に戻して書く
+//! - `sat bisect <input> <solver> [args...]` SATソルバ（`<solver> [args...] <cnf>` で呼び、
+//!   結果を標準出力に書くもの）でTを二分探索し、最短の操作列を書く
+use std::process::Command;
+
+use ahc042::board::*;
+use ahc042::sat::SatEncoding;
+use ahc042::solver::{is_valid_answer, Config, Solver};
+
+fn usage() -> ! {
+    let name = std::env::args().next().unwrap();
+    eprintln!("Usage: {} encode <input> <T>", name);
+    eprintln!("       {} decode <input> <T> <solver output>", name);
+    eprintln!("       {} bisect <input> <solver> [args...]", name);
+    std::process::exit(1)
+}
+
+fn read_file(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", path);
+        std::process::exit(1)
+    })
+}
+
+fn parse_horizon(s: &str) -> usize {
+    s.parse().unwrap_or_else(|err| {
+        eprintln!("T: {}", err);
+        std::process::exit(1)
+    })
+}
+
+/// 福を落とさずに全ての鬼を落とす操作列か
+fn is_valid(board_state: &BoardState, ans: &[Operation]) -> bool {
+    let ans = ans
+        .iter()
+        .map(|&operation| MacroOp::new(operation, 1))
+        .collect::<Vec<_>>();
+    is_valid_answer(board_state, &ans)
+}
+
+fn print_answer(ans: &[Operation]) {
+    for operation in ans {
+        println!("{}", operation);
+    }
+}
+
+/// T回以内の符号化をSATソルバで解く。充足不能ならNone
+fn run_solver(
+    board_state: &BoardState,
+    horizon: usize,
+    solver: &[String],
+) -> Option<Vec<Operation>> {
+    let encoding = SatEncoding::new(board_state.n, horizon);
+    let cnf = std::env::temp_dir().join(format!("ahc042-sat-{}.cnf", std::process::id()));
+    std::fs::write(&cnf, encoding.to_dimacs(board_state)).unwrap();
+    let output = Command::new(&solver[0])
+        .args(&solver[1..])
+        .arg(&cnf)
+        .output()
+        .unwrap_or_else(|err| {
+            eprintln!("failed to run {}: {}", solver[0], err);
+            std::process::exit(1)
+        });
+    let _ = std::fs::remove_file(&cnf);
+    encoding
+        .decode(&String::from_utf8_lossy(&output.stdout))
+        .unwrap_or_else(|err| {
+            eprintln!("invalid solver output: {}", err);
+            std::process::exit(1)
+        })
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() < 3 {
+        usage();
+    }
+    let board_state = parse_input(&read_file(&args[2])).unwrap_or_else(|err| {
+        eprintln!("invalid input: {}: {}", args[2], err);
+        std::process::exit(1)
+    });
+
+    match (args[1].as_str(), args.len()) {
+        ("encode", 4) => {
+            let encoding = SatEncoding::new(board_state.n, parse_horizon(&args[3]));
+            print!("{}", encoding.to_dimacs(&board_state));
+        }
+        ("decode", 5) => {
+            let encoding = SatEncoding::new(board_state.n, parse_horizon(&args[3]));
+            match encoding.decode(&read_file(&args[4])) {
+                Ok(Some(ans)) if is_valid(&board_state, &ans) => print_answer(&ans),
+                Ok(Some(_)) => {
+                    eprintln!("decoded answer is not valid");
+                    std::process::exit(1)
+                }
+                Ok(None) => {
+                    eprintln!("unsatisfiable");
+                    std::process::exit(1)
+                }
+                Err(err) => {
+                    eprintln!("invalid solver output: {}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+        ("bisect", 4..) => {
+            // 貪欲法の答えを上限に、lo回では解けずhi回で解ける範囲を狭める
+            let mut ans = expand(&Solver::new(Config::default()).solve(&board_state));
+            let mut lo = 0;
+            let mut hi = ans.len();
+            while hi - lo > 1 {
+                let mid = (lo + hi) / 2;
+                match run_solver(&board_state, mid, &args[3..]) {
+                    Some(res) => {
+                        if !is_valid(&board_state, &res) {
+                            eprintln!("decoded answer is not valid");
+                            std::process::exit(1)
+                        }
+                        eprintln!("T = {}: sat ({})", mid, res.len());
+                        hi = res.len();
+                        ans = res;
+                    }
+                    None => {
+                        eprintln!("T = {}: unsat", mid);
+                        lo = mid;
+                    }
+                }
+            }
+            eprintln!("optimal: {}", ans.len());
+            print_answer(&ans);
+        }
+        _ => usage(),
+    }
+}
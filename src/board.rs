@@ -0,0 +1,753 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoomState {
+    Oni = -1,
+    Fuku = 1,
+    Vacant = 0,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// L, R, U, D の順
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Direction::Left => "L",
+                Direction::Right => "R",
+                Direction::Up => "U",
+                Direction::Down => "D",
+            }
+        )
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Direction::ALL
+            .into_iter()
+            .find(|dir| dir.to_string() == s)
+            .ok_or_else(|| format!("invalid direction: {}", s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operation {
+    pub dir: Direction,
+    pub index: usize,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.dir, self.index)
+    }
+}
+
+/// 同じ操作をcount回続けたもの
+#[derive(Clone, Copy)]
+pub struct MacroOp {
+    pub dir: Direction,
+    pub index: usize,
+    pub count: usize,
+}
+
+impl MacroOp {
+    pub fn new(operation: Operation, count: usize) -> Self {
+        Self {
+            dir: operation.dir,
+            index: operation.index,
+            count,
+        }
+    }
+
+    pub fn operation(&self) -> Operation {
+        Operation {
+            dir: self.dir,
+            index: self.index,
+        }
+    }
+}
+
+/// (鬼の数, 福の数)
+fn count_pieces(rooms: impl Iterator<Item = RoomState>) -> (usize, usize) {
+    rooms.fold((0, 0), |(oni, fuku), room| match room {
+        RoomState::Oni => (oni + 1, fuku),
+        RoomState::Fuku => (oni, fuku + 1),
+        RoomState::Vacant => (oni, fuku),
+    })
+}
+
+/// 提出する形式の1回ずつの操作列に展開する
+pub fn expand(macro_ops: &[MacroOp]) -> Vec<Operation> {
+    macro_ops
+        .iter()
+        .flat_map(|macro_op| std::iter::repeat(macro_op.operation()).take(macro_op.count))
+        .collect()
+}
+
+/// 展開したときの操作回数
+pub fn num_operations(macro_ops: &[MacroOp]) -> usize {
+    macro_ops.iter().map(|macro_op| macro_op.count).sum()
+}
+
+#[derive(Clone, PartialEq)]
+pub struct BoardState {
+    pub n: usize,
+    pub board: Vec<Vec<RoomState>>,
+    pub num_oni: usize,
+    pub num_fuku: usize,
+}
+
+impl BoardState {
+    /// y行目の(begin, end)間に福がいるか
+    pub fn exists_fuku_in_row(&self, y: usize, begin: usize, end: usize) -> bool {
+        self.board[y][begin..end].contains(&RoomState::Fuku)
+    }
+
+    /// x列目の(begin, end)間に福がいるか
+    pub fn exists_fuku_in_col(&self, x: usize, begin: usize, end: usize) -> bool {
+        for i in begin..end {
+            if self.board[i][x] == RoomState::Fuku {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn exists_oni_around_all_fuku(&self) -> bool {
+        for i in 0..self.n {
+            for j in 0..self.n {
+                if self.board[i][j] != RoomState::Oni {
+                    continue;
+                }
+                assert_eq!(self.board[i][j], RoomState::Oni);
+                let can_fall = !self.exists_fuku_in_col(j, 0, i)
+                    || !self.exists_fuku_in_col(j, i + 1, self.n)
+                    || !self.exists_fuku_in_row(i, 0, j)
+                    || !self.exists_fuku_in_row(i, j + 1, self.n);
+                if !can_fall {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// (y, x) にいる鬼を福を落とさずに落とせる方向
+    pub fn escape_directions(&self, y: usize, x: usize) -> Vec<Direction> {
+        let mut dirs = Vec::new();
+        if !self.exists_fuku_in_row(y, 0, x) {
+            dirs.push(Direction::Left);
+        }
+        if !self.exists_fuku_in_row(y, x + 1, self.n) {
+            dirs.push(Direction::Right);
+        }
+        if !self.exists_fuku_in_col(x, 0, y) {
+            dirs.push(Direction::Up);
+        }
+        if !self.exists_fuku_in_col(x, y + 1, self.n) {
+            dirs.push(Direction::Down);
+        }
+        dirs
+    }
+
+    /// 鬼の位置 (y, x)、行優先順
+    pub fn oni_positions(&self) -> Vec<(usize, usize)> {
+        (0..self.n)
+            .flat_map(|i| (0..self.n).map(move |j| (i, j)))
+            .filter(|&(i, j)| self.board[i][j] == RoomState::Oni)
+            .collect()
+    }
+
+    /// 福のいない行と列の数
+    pub fn num_fuku_free_lines(&self) -> usize {
+        let rows = (0..self.n)
+            .filter(|&i| !self.exists_fuku_in_row(i, 0, self.n))
+            .count();
+        let cols = (0..self.n)
+            .filter(|&j| !self.exists_fuku_in_col(j, 0, self.n))
+            .count();
+        rows + cols
+    }
+
+    /// 行と列を入れ替えた盤面
+    pub fn transposed(&self) -> BoardState {
+        let mut board = vec![vec![RoomState::Vacant; self.n]; self.n];
+        for i in 0..self.n {
+            for j in 0..self.n {
+                board[j][i] = self.board[i][j];
+            }
+        }
+        BoardState {
+            board,
+            ..self.clone()
+        }
+    }
+
+    /// macro_opをまとめて適用する（1マスずつcount回ではなく、1回でcountマス動かす）
+    pub fn apply_macro(&mut self, macro_op: &MacroOp) {
+        let n = self.n;
+        let k = macro_op.count.min(n);
+        let index = macro_op.index;
+        // 落ちる (鬼の数, 福の数)
+        let fallen = match macro_op.dir {
+            Direction::Left => {
+                let row = &mut self.board[index];
+                let fallen = count_pieces(row[..k].iter().copied());
+                row.rotate_left(k);
+                row[n - k..].fill(RoomState::Vacant);
+                fallen
+            }
+            Direction::Right => {
+                let row = &mut self.board[index];
+                let fallen = count_pieces(row[n - k..].iter().copied());
+                row.rotate_right(k);
+                row[..k].fill(RoomState::Vacant);
+                fallen
+            }
+            Direction::Up => {
+                let fallen = count_pieces((0..k).map(|i| self.board[i][index]));
+                for i in 0..n - k {
+                    self.board[i][index] = self.board[i + k][index];
+                }
+                for i in n - k..n {
+                    self.board[i][index] = RoomState::Vacant;
+                }
+                fallen
+            }
+            Direction::Down => {
+                let fallen = count_pieces((n - k..n).map(|i| self.board[i][index]));
+                for i in (k..n).rev() {
+                    self.board[i][index] = self.board[i - k][index];
+                }
+                for i in 0..k {
+                    self.board[i][index] = RoomState::Vacant;
+                }
+                fallen
+            }
+        };
+        self.num_oni -= fallen.0;
+        self.num_fuku -= fallen.1;
+    }
+
+    pub fn apply(&mut self, operation: &Operation) {
+        match operation.dir {
+            Direction::Left => {
+                match self.board[operation.index][0] {
+                    RoomState::Oni => self.num_oni -= 1,
+                    RoomState::Fuku => self.num_fuku -= 1,
+                    RoomState::Vacant => (),
+                }
+                for j in 0..self.n - 1 {
+                    self.board[operation.index][j] = self.board[operation.index][j + 1];
+                }
+                self.board[operation.index][self.n - 1] = RoomState::Vacant;
+            }
+            Direction::Right => {
+                match self.board[operation.index][self.n - 1] {
+                    RoomState::Oni => self.num_oni -= 1,
+                    RoomState::Fuku => self.num_fuku -= 1,
+                    RoomState::Vacant => (),
+                }
+                for j in (1..self.n).rev() {
+                    self.board[operation.index][j] = self.board[operation.index][j - 1];
+                }
+                self.board[operation.index][0] = RoomState::Vacant;
+            }
+            Direction::Up => {
+                match self.board[0][operation.index] {
+                    RoomState::Oni => self.num_oni -= 1,
+                    RoomState::Fuku => self.num_fuku -= 1,
+                    RoomState::Vacant => (),
+                }
+                for i in 0..self.n - 1 {
+                    self.board[i][operation.index] = self.board[i + 1][operation.index];
+                }
+                self.board[self.n - 1][operation.index] = RoomState::Vacant;
+            }
+            Direction::Down => {
+                match self.board[self.n - 1][operation.index] {
+                    RoomState::Oni => self.num_oni -= 1,
+                    RoomState::Fuku => self.num_fuku -= 1,
+                    RoomState::Vacant => (),
+                }
+                for i in (1..self.n).rev() {
+                    self.board[i][operation.index] = self.board[i - 1][operation.index];
+                }
+                self.board[0][operation.index] = RoomState::Vacant;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pretty_print_board_row = |row: &Vec<RoomState>| -> String {
+            let mut s = row
+                .iter()
+                .map(|rs| match rs {
+                    RoomState::Oni => "x",
+                    RoomState::Fuku => "o",
+                    RoomState::Vacant => ".",
+                })
+                .collect::<String>();
+            s.push('\n');
+            s
+        };
+        let str_board: String = self.board.iter().map(pretty_print_board_row).collect();
+        write!(f, "{}", str_board)
+    }
+}
+
+/// x, o, . で盤面を表す文字列からBoardStateを生成する
+///
+/// 空白区切りでちょうど board_size 行、各行ちょうど board_size 文字でなければエラーを返す
+pub fn generate_board_from_string(
+    board_size: usize,
+    board_str: &str,
+) -> Result<BoardState, String> {
+    let board_rows = board_str.split_whitespace().collect::<Vec<_>>();
+    if board_rows.len() != board_size {
+        return Err(format!(
+            "expected {} rows, found {}",
+            board_size,
+            board_rows.len()
+        ));
+    }
+    let mut board = Vec::with_capacity(board_size);
+    let mut num_oni = 0;
+    let mut num_fuku = 0;
+    for (i, row) in board_rows.into_iter().enumerate() {
+        let row = row
+            .chars()
+            .map(|room| match room {
+                'x' => {
+                    num_oni += 1;
+                    Ok(RoomState::Oni)
+                }
+                'o' => {
+                    num_fuku += 1;
+                    Ok(RoomState::Fuku)
+                }
+                '.' => Ok(RoomState::Vacant),
+                _ => Err(format!("row {}: invalid cell: {}", i, room)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if row.len() != board_size {
+            return Err(format!(
+                "row {}: expected {} cells, found {}",
+                i,
+                board_size,
+                row.len()
+            ));
+        }
+        board.push(row);
+    }
+    Ok(BoardState {
+        n: board_size,
+        board,
+        num_oni,
+        num_fuku,
+    })
+}
+
+/// 入力形式（1行目にN、続くN行に盤面）の文字列からBoardStateを生成する
+pub fn parse_input(input: &str) -> Result<BoardState, String> {
+    let Some((n, board_str)) = input.trim_start().split_once(char::is_whitespace) else {
+        return Err("unexpected end of input".to_owned());
+    };
+    let n = match n.parse() {
+        Ok(n) if n > 0 => n,
+        _ => return Err(format!("invalid board size: {}", n)),
+    };
+    generate_board_from_string(n, board_str)
+}
+
+/// 操作回数t、残った鬼の数x、落ちた福の数yのときの得点
+pub fn compute_score(n: usize, t: usize, x: usize, y: usize) -> i64 {
+    if x == 0 && y == 0 {
+        (8 * n * n) as i64 - t as i64
+    } else {
+        (4 * n * n) as i64 - (n * (x + y)) as i64
+    }
+}
+
+/// board_stateから操作列ansを順に動かしたときの得点。上限 4N^2 を超えた操作列は0点
+///
+/// 公式ツールの `compute_score_details` と同じ値になることをテストで確かめている。
+pub fn compute_score_of(board_state: &BoardState, ans: &[Operation]) -> i64 {
+    let n = board_state.n;
+    if ans.len() > 4 * n * n {
+        return 0;
+    }
+    let mut after_board = board_state.clone();
+    for operation in ans {
+        after_board.apply(operation);
+    }
+    compute_score(
+        n,
+        ans.len(),
+        after_board.num_oni,
+        board_state.num_fuku - after_board.num_fuku,
+    )
+}
+
+pub fn get_rev_dir(dir: Direction) -> Direction {
+    match dir {
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use std::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_board_from_string() {
+        let board = generate_board_from_string(
+            3,
+            r"
+            x.o
+            o.x
+            .xo
+        ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            board,
+            BoardState {
+                n: 3,
+                board: vec![
+                    vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
+                    vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                    vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+                ],
+                num_oni: 3,
+                num_fuku: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_generate_board_from_string_4x4() {
+        let board = generate_board_from_string(
+            4,
+            r"
+            x.ox
+            o.x.
+            .xo.
+            o.x.
+        ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            board,
+            BoardState {
+                n: 4,
+                board: vec![
+                    vec![
+                        RoomState::Oni,
+                        RoomState::Vacant,
+                        RoomState::Fuku,
+                        RoomState::Oni
+                    ],
+                    vec![
+                        RoomState::Fuku,
+                        RoomState::Vacant,
+                        RoomState::Oni,
+                        RoomState::Vacant
+                    ],
+                    vec![
+                        RoomState::Vacant,
+                        RoomState::Oni,
+                        RoomState::Fuku,
+                        RoomState::Vacant
+                    ],
+                    vec![
+                        RoomState::Fuku,
+                        RoomState::Vacant,
+                        RoomState::Oni,
+                        RoomState::Vacant
+                    ],
+                ],
+                num_oni: 5,
+                num_fuku: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_apply_operation_left() {
+        let mut board_state = BoardState {
+            n: 3,
+            board: vec![
+                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ],
+            num_oni: 3,
+            num_fuku: 3,
+        };
+
+        board_state.apply(&Operation {
+            dir: Direction::Left,
+            index: 0,
+        });
+
+        assert_eq!(
+            board_state.board,
+            vec![
+                vec![RoomState::Vacant, RoomState::Fuku, RoomState::Vacant],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ]
+        );
+        assert_eq!(board_state.num_oni, 2);
+        assert_eq!(board_state.num_fuku, 3);
+    }
+
+    #[test]
+    fn test_apply_operation_right() {
+        let mut board_state = BoardState {
+            n: 3,
+            board: vec![
+                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ],
+            num_oni: 3,
+            num_fuku: 3,
+        };
+
+        board_state.apply(&Operation {
+            dir: Direction::Right,
+            index: 0,
+        });
+
+        assert_eq!(
+            board_state.board,
+            vec![
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Vacant],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ]
+        );
+        assert_eq!(board_state.num_oni, 3);
+        assert_eq!(board_state.num_fuku, 2);
+    }
+
+    #[test]
+    fn test_apply_operation_up() {
+        let mut board_state = BoardState {
+            n: 3,
+            board: vec![
+                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ],
+            num_oni: 3,
+            num_fuku: 3,
+        };
+
+        board_state.apply(&Operation {
+            dir: Direction::Up,
+            index: 0,
+        });
+
+        assert_eq!(
+            board_state.board,
+            vec![
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Vacant, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ]
+        );
+        assert_eq!(board_state.num_oni, 2);
+        assert_eq!(board_state.num_fuku, 3);
+    }
+
+    #[test]
+    fn test_apply_operation_down() {
+        let mut board_state = BoardState {
+            n: 3,
+            board: vec![
+                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
+            ],
+            num_oni: 3,
+            num_fuku: 3,
+        };
+
+        board_state.apply(&Operation {
+            dir: Direction::Down,
+            index: 2,
+        });
+
+        assert_eq!(
+            board_state.board,
+            vec![
+                vec![RoomState::Oni, RoomState::Vacant, RoomState::Vacant],
+                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Fuku],
+                vec![RoomState::Vacant, RoomState::Oni, RoomState::Oni],
+            ]
+        );
+        assert_eq!(board_state.num_oni, 3);
+        assert_eq!(board_state.num_fuku, 2);
+    }
+
+    #[test]
+    fn test_escape_directions() {
+        let board_state = generate_board_from_string(
+            3,
+            r"
+            x.o
+            o.x
+            .xo
+        ",
+        )
+        .unwrap();
+
+        let dirs = |y, x| {
+            board_state
+                .escape_directions(y, x)
+                .iter()
+                .map(|dir| dir.to_string())
+                .collect::<String>()
+        };
+        assert_eq!(dirs(0, 0), "LU");
+        assert_eq!(dirs(1, 2), "R");
+        assert_eq!(dirs(2, 1), "LUD");
+    }
+
+    #[test]
+    fn test_direction_from_str() {
+        for dir in Direction::ALL {
+            assert_eq!(
+                dir.to_string().parse::<Direction>().unwrap().to_string(),
+                dir.to_string()
+            );
+        }
+        assert!("X".parse::<Direction>().is_err());
+        assert!("".parse::<Direction>().is_err());
+    }
+
+    #[test]
+    fn test_parse_input_error() {
+        assert!(parse_input("3\nx.o\no.x\n.xo\n").is_ok());
+        assert!(parse_input("").is_err());
+        assert!(parse_input("3").is_err());
+        assert!(parse_input("-1\n").is_err());
+        assert!(parse_input("0\n").is_err());
+        // 行が足りない・長さが違う・知らない文字
+        assert_eq!(
+            parse_input("3\nx.o\no.x\n").err(),
+            Some("expected 3 rows, found 2".to_owned())
+        );
+        assert_eq!(
+            parse_input("3\nx.o\no.x\n.xox\n").err(),
+            Some("row 2: expected 3 cells, found 4".to_owned())
+        );
+        assert_eq!(
+            parse_input("3\nx.o\no.\n.xo\n").err(),
+            Some("row 1: expected 3 cells, found 2".to_owned())
+        );
+        assert_eq!(
+            parse_input("3\nx.o\no.x\n.xa\n").err(),
+            Some("row 2: invalid cell: a".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_apply_macro() {
+        let board_state = generate_board_from_string(
+            4,
+            r"
+            x.ox
+            o.x.
+            .xo.
+            o.x.
+        ",
+        )
+        .unwrap();
+
+        // まとめて動かした盤面と1マスずつ動かした盤面は一致する
+        for dir in Direction::ALL {
+            for index in 0..4 {
+                for count in 0..6 {
+                    let mut expected = board_state.clone();
+                    for _ in 0..count {
+                        expected.apply(&Operation { dir, index });
+                    }
+                    let mut actual = board_state.clone();
+                    actual.apply_macro(&MacroOp { dir, index, count });
+                    assert_eq!(actual, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_score_of_matches_tools() {
+        use rand::prelude::*;
+        use rand_chacha::ChaCha20Rng;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut dropped_fuku = false;
+        for seed in 0..5 {
+            let input = tools::gen(seed);
+            let board_state = parse_input(&input.to_string()).unwrap();
+            let n = board_state.n;
+            // 上限ちょうどと上限を1つ超えた長さも試す
+            for len in [0, 1, 10, 100, 4 * n * n, 4 * n * n + 1] {
+                let ans = (0..len)
+                    .map(|_| Operation {
+                        dir: Direction::ALL[rng.gen_range(0..4)],
+                        index: rng.gen_range(0..n),
+                    })
+                    .collect::<Vec<_>>();
+                let output = ans.iter().map(|op| format!("{}\n", op)).collect::<String>();
+                let expected = tools::parse_output(&input, &output).map_or(0, |output| {
+                    tools::compute_score_details(&input, &output.out).0
+                });
+                assert_eq!(compute_score_of(&board_state, &ans), expected);
+
+                let mut after_board = board_state.clone();
+                for operation in &ans {
+                    after_board.apply(operation);
+                }
+                dropped_fuku |= after_board.num_fuku < board_state.num_fuku;
+            }
+        }
+        assert!(dropped_fuku);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::board::*;
+use crate::parking::park_fuku;
+use crate::solver::Solver;
+
+/// 掃き出す方向が常に「行を左へ」になるように盤面を見る座標系
+///
+/// 変換後の (i, j) は、j を左右反転してから転置した元の盤面のマスに対応する。
+#[derive(Clone, Copy)]
+struct Frame {
+    transpose: bool,
+    mirror: bool,
+}
+
+impl Frame {
+    const ALL: [Frame; 4] = [
+        Frame {
+            transpose: false,
+            mirror: false,
+        },
+        Frame {
+            transpose: false,
+            mirror: true,
+        },
+        Frame {
+            transpose: true,
+            mirror: false,
+        },
+        Frame {
+            transpose: true,
+            mirror: true,
+        },
+    ];
+
+    fn grid(&self, board_state: &BoardState) -> Vec<Vec<RoomState>> {
+        let n = board_state.n;
+        let mut grid = vec![vec![RoomState::Vacant; n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                let j2 = if self.mirror { n - 1 - j } else { j };
+                grid[i][j] = if self.transpose {
+                    board_state.board[j2][i]
+                } else {
+                    board_state.board[i][j2]
+                };
+            }
+        }
+        grid
+    }
+
+    /// 変換後の座標系での操作を元の盤面での操作に直す
+    fn operation(&self, dir: Direction, index: usize, n: usize) -> Operation {
+        let (dir, index) = match (self.mirror, dir) {
+            (true, Direction::Left) => (Direction::Right, index),
+            (true, Direction::Right) => (Direction::Left, index),
+            (true, _) => (dir, n - 1 - index),
+            (false, _) => (dir, index),
+        };
+        let dir = match (self.transpose, dir) {
+            (true, Direction::Left) => Direction::Up,
+            (true, Direction::Right) => Direction::Down,
+            (true, Direction::Up) => Direction::Left,
+            (true, Direction::Down) => Direction::Right,
+            (false, _) => dir,
+        };
+        Operation { dir, index }
+    }
+}
+
+/// 列を縦に動かして鬼をr行目に寄せる方法
+#[derive(Clone, Copy)]
+struct Gather {
+    /// 動かす回数（0ならすでにr行目に鬼がいる）
+    count: usize,
+    dir: Direction,
+}
+
+/// r行目のj列目に鬼を寄せる一番安い方法（端から福を落とさないものに限る）
+fn cheapest_gather(grid: &[Vec<RoomState>], r: usize, j: usize) -> Option<Gather> {
+    let n = grid.len();
+    if grid[r][j] == RoomState::Oni {
+        return Some(Gather {
+            count: 0,
+            dir: Direction::Up,
+        });
+    }
+    for count in 1..n {
+        // 上に動かすと 0..count 行目が落ちる
+        if r + count < n
+            && grid[r + count][j] == RoomState::Oni
+            && (0..count).all(|i| grid[i][j] != RoomState::Fuku)
+        {
+            return Some(Gather {
+                count,
+                dir: Direction::Up,
+            });
+        }
+        // 下に動かすと n - count..n 行目が落ちる
+        if count <= r
+            && grid[r - count][j] == RoomState::Oni
+            && (n - count..n).all(|i| grid[i][j] != RoomState::Fuku)
+        {
+            return Some(Gather {
+                count,
+                dir: Direction::Down,
+            });
+        }
+    }
+    None
+}
+
+/// 変換後の座標系でr行目に鬼を寄せてから左にk回掃き出す計画
+struct Plan {
+    frame: Frame,
+    r: usize,
+    k: usize,
+    /// 寄せる列とその方法
+    gathers: Vec<(usize, Gather)>,
+    num_oni: usize,
+    num_op: usize,
+}
+
+impl Plan {
+    fn operations(&self, n: usize) -> Vec<MacroOp> {
+        let mut res = Vec::new();
+        for &(j, gather) in &self.gathers {
+            if gather.count > 0 {
+                res.push(MacroOp::new(
+                    self.frame.operation(gather.dir, j, n),
+                    gather.count,
+                ));
+            }
+        }
+        res.push(MacroOp::new(
+            self.frame.operation(Direction::Left, self.r, n),
+            self.k,
+        ));
+        res
+    }
+}
+
+/// 全ての座標系・行・掃き出す長さについて、鬼を寄せてから掃き出す計画を列挙する
+///
+/// 寄せるのにthreshold回以下しかかからない列だけを寄せるとして、thresholdも全通り試す。
+fn enumerate_plans(board_state: &BoardState) -> Vec<Plan> {
+    let n = board_state.n;
+    let mut plans = Vec::new();
+    for frame in Frame::ALL {
+        let grid = frame.grid(board_state);
+        for r in 0..n {
+            let gathers = (0..n)
+                .map(|j| cheapest_gather(&grid, r, j))
+                .collect::<Vec<_>>();
+            for threshold in 0..n {
+                let mut selected = Vec::new();
+                let mut num_oni = 0;
+                let mut num_op = 0;
+                for j in 0..n {
+                    match gathers[j] {
+                        Some(gather) if gather.count <= threshold => {
+                            selected.push((j, gather));
+                            num_oni += 1;
+                            num_op += gather.count;
+                        }
+                        // 掃き出すときに福が落ちるのでこれ以上は伸ばせない
+                        _ if grid[r][j] == RoomState::Fuku => break,
+                        _ => continue,
+                    }
+                    plans.push(Plan {
+                        frame,
+                        r,
+                        k: j + 1,
+                        gathers: selected.clone(),
+                        num_oni,
+                        num_op: num_op + j + 1,
+                    });
+                }
+            }
+        }
+    }
+    plans
+}
+
+/// 鬼をまとめて1つの行（列）に寄せてから端に掃き出すことを繰り返す
+///
+/// 1体あたりの操作回数が `Solver::fall_oni` より少ない計画があればそれを使い、
+/// なければ `Solver::fall_oni` で1体落とす。
+/// 計画はシミュレーションして、福が落ちないことと残りの鬼が全て落とせることを確認する。
+/// SIGINTかSIGTERMを受け取るかsolver.deadlineを過ぎたら、鬼が残っていてもそこまでの操作列を返す。
+pub fn solve_gather(board_state: &BoardState, solver: &mut Solver) -> Vec<MacroOp> {
+    let n = board_state.n;
+    let mut board_state = board_state.clone();
+    let mut ans = Vec::new();
+
+    while board_state.num_oni > 0 {
+        // 打ち切られたらそこまでの操作列を返す
+        if solver.should_stop() {
+            break;
+        }
+        if solver.config.parking {
+            ans.extend(park_fuku(&mut board_state));
+            if board_state.num_oni == 0 {
+                break;
+            }
+        }
+        let (y, x) = solver.next_oni(&board_state);
+        let mut operations = solver.fall_oni(y, x, &board_state);
+
+        let mut plans = enumerate_plans(&board_state);
+        // 1体あたりの操作回数が少ない順、同じなら多く落とせる順
+        plans.sort_by(|a, b| {
+            (a.num_op * b.num_oni)
+                .cmp(&(b.num_op * a.num_oni))
+                .then(b.num_oni.cmp(&a.num_oni))
+        });
+        for plan in &plans {
+            if plan.num_op >= num_operations(&operations) * plan.num_oni {
+                break;
+            }
+            let plan_operations = plan.operations(n);
+            let mut after_board = board_state.clone();
+            let mut lost_fuku = false;
+            for macro_op in &plan_operations {
+                after_board.apply_macro(macro_op);
+                lost_fuku |= after_board.num_fuku < board_state.num_fuku;
+            }
+            if !lost_fuku && !after_board.exists_oni_around_all_fuku() {
+                operations = plan_operations;
+                break;
+            }
+        }
+
+        for macro_op in operations {
+            board_state.apply_macro(&macro_op);
+            ans.push(macro_op);
+        }
+    }
+
+    ans
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+    use crate::solver::Config;
+
+    #[test]
+    fn test_frame_operation() {
+        let board_state = generate_board_from_string(
+            3,
+            r"
+            x.o
+            o.x
+            .xo
+        ",
+        )
+        .unwrap();
+
+        for frame in Frame::ALL {
+            for dir in Direction::ALL {
+                for index in 0..3 {
+                    // 変換後の座標系で動かした盤面と、元の盤面で対応する操作をした盤面は一致する
+                    let mut expected = BoardState {
+                        board: frame.grid(&board_state),
+                        ..board_state.clone()
+                    };
+                    expected.apply(&Operation { dir, index });
+
+                    let mut actual = board_state.clone();
+                    actual.apply(&frame.operation(dir, index, 3));
+
+                    assert_eq!(frame.grid(&actual), expected.board);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve_gather() {
+        let board_state = generate_board_from_string(
+            6,
+            r"
+            .x..x.
+            o..x..
+            .x.o.x
+            x...o.
+            ..x..o
+            .o..x.
+        ",
+        )
+        .unwrap();
+
+        let mut after_board = board_state.clone();
+        for macro_op in solve_gather(&board_state, &mut Solver::new(Config::default())) {
+            after_board.apply_macro(&macro_op);
+        }
+
+        assert_eq!(after_board.num_oni, 0);
+        assert_eq!(after_board.num_fuku, board_state.num_fuku);
+    }
+}
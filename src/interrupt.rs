@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+    use std::sync::atomic::Ordering;
+
+    pub const SIGINT: c_int = 2;
+    pub const SIGTERM: c_int = 15;
+
+    /// シグナルハンドラ。Noneは `SIG_DFL`（ヌルポインタ）
+    pub type SigHandler = Option<extern "C" fn(c_int)>;
+    pub const SIG_DFL: SigHandler = None;
+
+    extern "C" {
+        pub fn signal(signum: c_int, handler: SigHandler) -> SigHandler;
+    }
+
+    pub extern "C" fn handler(signum: c_int) {
+        super::INTERRUPTED.store(true, Ordering::SeqCst);
+        // 2回目はそのまま終了させる
+        unsafe {
+            signal(signum, SIG_DFL);
+        }
+    }
+}
+
+/// SIGINTとSIGTERMを受け取ったら `is_interrupted` がtrueになるようにする
+///
+/// 探索はこれを見て打ち切り、それまでの一番良い解を返す。2回目のシグナルではそのまま終了する。
+pub fn install_handler() {
+    #[cfg(unix)]
+    unsafe {
+        sys::signal(sys::SIGINT, Some(sys::handler));
+        sys::signal(sys::SIGTERM, Some(sys::handler));
+    }
+}
+
+/// SIGINTかSIGTERMを受け取ったのと同じ状態にする
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// SIGINTかSIGTERMを受け取ったか
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
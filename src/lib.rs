@@ -0,0 +1,14 @@
+#![allow(clippy::needless_range_loop, clippy::ptr_arg)]
+
+pub mod analysis;
+pub mod board;
+pub mod gather;
+pub mod interrupt;
+pub mod local_search;
+pub mod mcts;
+pub mod opening;
+pub mod parking;
+pub mod portfolio;
+pub mod sat;
+pub mod solver;
+pub mod truncation;
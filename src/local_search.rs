@@ -0,0 +1,144 @@
+use std::time::Instant;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+use crate::board::*;
+use crate::interrupt::is_interrupted;
+
+/// 焼きなましの開始時と終了時の温度（得点の差の単位）
+const START_TEMP: f64 = 3.0;
+const END_TEMP: f64 = 0.2;
+
+/// 提出する操作列そのものを焼きなましてdeadlineまで改善し、一番得点の高い操作列を返す
+///
+/// 近傍は操作の挿入・削除・行（列）の変更・向きの変更・隣り合う操作の交換で、
+/// 得点は公式ツールと同じ値を返す `compute_score_of` で毎回計算し直す（ツールの入出力を介さない分速い）。操作回数は上限 4N^2 を超えない。
+/// SIGINTかSIGTERMを受け取ったらdeadline前でも打ち切る。
+pub fn optimize(
+    board_state: &BoardState,
+    ans: &[Operation],
+    deadline: Instant,
+    rng: &mut ChaCha20Rng,
+) -> Vec<Operation> {
+    let start = Instant::now();
+    let total = deadline.saturating_duration_since(start).as_secs_f64();
+    anneal(board_state, ans, rng, |_| {
+        let now = Instant::now();
+        if now >= deadline || is_interrupted() {
+            return None;
+        }
+        Some((now - start).as_secs_f64() / total)
+    })
+}
+
+/// 時間の代わりにおよそiterations回で打ち切る `optimize`。結果は実行ごとに変わらない
+#[cfg(test)]
+fn optimize_iterations(
+    board_state: &BoardState,
+    ans: &[Operation],
+    iterations: usize,
+    rng: &mut ChaCha20Rng,
+) -> Vec<Operation> {
+    anneal(board_state, ans, rng, |iteration| {
+        (iteration < iterations).then_some(iteration as f64 / iterations as f64)
+    })
+}
+
+/// 焼きなましの本体。progressは64回ごとに反復回数を受け取り、進み具合（0から1）か、打ち切るならNoneを返す
+fn anneal(
+    board_state: &BoardState,
+    ans: &[Operation],
+    rng: &mut ChaCha20Rng,
+    mut progress: impl FnMut(usize) -> Option<f64>,
+) -> Vec<Operation> {
+    let n = board_state.n;
+    let max_len = 4 * n * n;
+    let mut current = ans.to_vec();
+    let mut current_score = compute_score_of(board_state, &current);
+    let mut best = current.clone();
+    let mut best_score = current_score;
+
+    let mut temp = START_TEMP;
+    for iteration in 0.. {
+        if iteration % 64 == 0 {
+            let Some(progress) = progress(iteration) else {
+                break;
+            };
+            temp = START_TEMP * (END_TEMP / START_TEMP).powf(progress);
+        }
+
+        let len = current.len();
+        let mut next = current.clone();
+        let random_op = |rng: &mut ChaCha20Rng| Operation {
+            dir: Direction::ALL[rng.gen_range(0..4)],
+            index: rng.gen_range(0..n),
+        };
+        match rng.gen_range(0..5) {
+            // 挿入
+            0 if len < max_len => next.insert(rng.gen_range(0..=len), random_op(rng)),
+            // 削除
+            1 if len > 0 => {
+                next.remove(rng.gen_range(0..len));
+            }
+            // 行（列）の変更
+            2 if len > 0 => next[rng.gen_range(0..len)].index = rng.gen_range(0..n),
+            // 向きの変更
+            3 if len > 0 => next[rng.gen_range(0..len)].dir = Direction::ALL[rng.gen_range(0..4)],
+            // 隣り合う操作の交換
+            4 if len > 1 => {
+                let i = rng.gen_range(0..len - 1);
+                next.swap(i, i + 1);
+            }
+            _ => continue,
+        }
+
+        let next_score = compute_score_of(board_state, &next);
+        let delta = next_score - current_score;
+        if delta >= 0 || rng.gen::<f64>() < (delta as f64 / temp).exp() {
+            current = next;
+            current_score = next_score;
+            if current_score > best_score {
+                best = current.clone();
+                best_score = current_score;
+            }
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_optimize() {
+        let board_state = generate_board_from_string(
+            3,
+            r"
+            ...
+            x.o
+            .xo
+        ",
+        )
+        .unwrap();
+        // 2回で済むところに、福のいない0行目を動かすだけの無駄な操作を足す
+        let ans = ["L 1", "L 0", "D 1", "R 0"]
+            .iter()
+            .map(|s| {
+                let (d, index) = s.split_once(' ').unwrap();
+                Operation {
+                    dir: d.parse().unwrap(),
+                    index: index.parse().unwrap(),
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(compute_score_of(&board_state, &ans), 8 * 9 - 4);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let optimized = optimize_iterations(&board_state, &ans, 10000, &mut rng);
+
+        assert_eq!(compute_score_of(&board_state, &optimized), 8 * 9 - 2);
+    }
+}
@@ -1,521 +1,122 @@
-#![allow(clippy::needless_range_loop, clippy::ptr_arg)]
-use std::{fmt, io::Read};
+use std::io::Read;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-const BOARD_SIZE: usize = 20;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum RoomState {
-    Oni = -1,
-    Fuku = 1,
-    Vacant = 0,
-}
-
-#[derive(Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
-}
-
-impl fmt::Display for Direction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Direction::Left => "L",
-                Direction::Right => "R",
-                Direction::Up => "U",
-                Direction::Down => "D",
-            }
-        )
-    }
-}
-
-#[derive(Clone, Copy)]
-struct Operation {
-    dir: Direction,
-    index: usize,
-}
-
-impl fmt::Display for Operation {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {}", self.dir, self.index)
-    }
-}
-
-#[derive(Clone, PartialEq)]
-struct BoardState {
-    n: usize,
-    board: Vec<Vec<RoomState>>,
-    num_oni: usize,
-    num_fuku: usize,
-}
-
-impl BoardState {
-    /// y行目の(begin, end)間に福がいるか
-    fn exists_fuku_in_row(&self, y: usize, begin: usize, end: usize) -> bool {
-        self.board[y][begin..end].contains(&RoomState::Fuku)
-    }
-
-    /// x列目の(begin, end)間に福がいるか
-    fn exists_fuku_in_col(&self, x: usize, begin: usize, end: usize) -> bool {
-        for i in begin..end {
-            if self.board[i][x] == RoomState::Fuku {
-                return true;
-            }
-        }
-        false
-    }
-
-    fn exists_oni_around_all_fuku(&self) -> bool {
-        for i in 0..self.n {
-            for j in 0..self.n {
-                if self.board[i][j] != RoomState::Oni {
-                    continue;
-                }
-                assert_eq!(self.board[i][j], RoomState::Oni);
-                let can_fall = !self.exists_fuku_in_col(j, 0, i)
-                    || !self.exists_fuku_in_col(j, i + 1, self.n)
-                    || !self.exists_fuku_in_row(i, 0, j)
-                    || !self.exists_fuku_in_row(i, j + 1, self.n);
-                if !can_fall {
-                    return true;
-                }
-            }
-        }
-
-        false
-    }
-
-    fn apply(&mut self, operation: &Operation) {
-        match operation.dir {
-            Direction::Left => {
-                match self.board[operation.index][0] {
-                    RoomState::Oni => self.num_oni -= 1,
-                    RoomState::Fuku => self.num_fuku -= 1,
-                    RoomState::Vacant => (),
-                }
-                for j in 0..self.n - 1 {
-                    self.board[operation.index][j] = self.board[operation.index][j + 1];
-                }
-                self.board[operation.index][self.n - 1] = RoomState::Vacant;
-            }
-            Direction::Right => {
-                match self.board[operation.index][self.n - 1] {
-                    RoomState::Oni => self.num_oni -= 1,
-                    RoomState::Fuku => self.num_fuku -= 1,
-                    RoomState::Vacant => (),
-                }
-                for j in (1..self.n).rev() {
-                    self.board[operation.index][j] = self.board[operation.index][j - 1];
-                }
-                self.board[operation.index][0] = RoomState::Vacant;
-            }
-            Direction::Up => {
-                match self.board[0][operation.index] {
-                    RoomState::Oni => self.num_oni -= 1,
-                    RoomState::Fuku => self.num_fuku -= 1,
-                    RoomState::Vacant => (),
-                }
-                for i in 0..self.n - 1 {
-                    self.board[i][operation.index] = self.board[i + 1][operation.index];
-                }
-                self.board[self.n - 1][operation.index] = RoomState::Vacant;
-            }
-            Direction::Down => {
-                match self.board[self.n - 1][operation.index] {
-                    RoomState::Oni => self.num_oni -= 1,
-                    RoomState::Fuku => self.num_fuku -= 1,
-                    RoomState::Vacant => (),
-                }
-                for i in (1..self.n).rev() {
-                    self.board[i][operation.index] = self.board[i - 1][operation.index];
-                }
-                self.board[0][operation.index] = RoomState::Vacant;
-            }
-        }
-    }
-}
-
-impl fmt::Debug for BoardState {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let pretty_print_board_row = |row: &Vec<RoomState>| -> String {
-            let mut s = row
-                .iter()
-                .map(|rs| match rs {
-                    RoomState::Oni => "x",
-                    RoomState::Fuku => "o",
-                    RoomState::Vacant => ".",
-                })
-                .collect::<String>();
-            s.push('\n');
-            s
-        };
-        let str_board: String = self.board.iter().map(pretty_print_board_row).collect();
-        write!(f, "{}", str_board)
-    }
-}
-
-/// x, o, . で盤面を表す文字列からBoardStateを生成する
-fn generate_board_from_string(board_size: usize, board_str: &str) -> BoardState {
-    let board_rows = board_str.split_whitespace();
-    let mut board = vec![vec![RoomState::Vacant; board_size]; board_size];
-    let mut num_oni = 0;
-    let mut num_fuku = 0;
-    for (i, row) in board_rows.enumerate() {
-        let row = row.chars();
-        for (j, room) in row.enumerate() {
-            board[i][j] = match room {
-                'x' => {
-                    num_oni += 1;
-                    RoomState::Oni
-                }
-                'o' => {
-                    num_fuku += 1;
-                    RoomState::Fuku
-                }
-                '.' => RoomState::Vacant,
-                _ => unreachable!(),
-            };
-        }
-    }
-    BoardState {
-        n: board_size,
-        board,
-        num_oni,
-        num_fuku,
-    }
-}
+use ahc042::board::*;
+use ahc042::interrupt;
+use ahc042::local_search;
+use ahc042::portfolio::{greedy_fallback, solve_portfolio};
+use ahc042::solver::{Config, Solver};
+use ahc042::truncation;
 
 fn input_parser() -> BoardState {
     let mut buf = String::new();
-
-    let _ = std::io::stdin().read_line(&mut buf).is_ok();
-    let n = buf.trim().parse::<usize>().unwrap();
-    buf.clear();
-
-    assert_eq!(n, BOARD_SIZE);
-
     let _ = std::io::stdin().read_to_string(&mut buf).is_ok();
-    let board_str = &buf;
-
-    generate_board_from_string(n, board_str)
+    parse_input(&buf).unwrap_or_else(|err| {
+        eprintln!("invalid input: {}", err);
+        std::process::exit(1)
+    })
 }
 
-fn find_oni(board_state: &BoardState) -> (usize, usize) {
-    assert!(board_state.num_oni > 0);
-    for i in 0..board_state.n {
-        for j in 0..board_state.n {
-            if board_state.board[i][j] == RoomState::Oni {
-                return (i, j);
-            }
-        }
-    }
-    unreachable!()
+/// `<name> <value>` の形の引数を読む
+fn parse_arg<T: FromStr>(name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    let args = std::env::args().collect::<Vec<_>>();
+    let i = args.iter().position(|arg| arg == name)?;
+    let value = args.get(i + 1).map_or("", |value| value.as_str());
+    Some(value.parse().unwrap_or_else(|err| {
+        eprintln!("{}: {}", name, err);
+        std::process::exit(1)
+    }))
 }
 
-fn get_rev_dir(dir: Direction) -> Direction {
-    match dir {
-        Direction::Left => Direction::Right,
-        Direction::Right => Direction::Left,
-        Direction::Up => Direction::Down,
-        Direction::Down => Direction::Up,
-    }
+fn has_flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
 }
 
-/// (y, x) にいる鬼を一番近い端に落としてから初期盤面に戻すという操作列を返す
-fn fall_oni_and_reset(y: usize, x: usize, board_state: &BoardState) -> Vec<Operation> {
-    assert_eq!(board_state.board[y][x], RoomState::Oni);
-
-    let mut dir = None;
-    let mut min_op = 1000;
-    let mut index = 0;
-
-    if !board_state.exists_fuku_in_row(y, 0, x) && x + 1 < min_op {
-        dir = Some(Direction::Left);
-        min_op = x + 1;
-        index = y;
-    }
-
-    if !board_state.exists_fuku_in_row(y, x + 1, board_state.n) && board_state.n - x < min_op {
-        dir = Some(Direction::Right);
-        min_op = board_state.n - x;
-        index = y;
-    }
-
-    if !board_state.exists_fuku_in_col(x, 0, y) && y + 1 < min_op {
-        dir = Some(Direction::Up);
-        min_op = y + 1;
-        index = x;
-    }
-
-    if !board_state.exists_fuku_in_col(x, y + 1, board_state.n) && board_state.n - y < min_op {
-        dir = Some(Direction::Down);
-        min_op = board_state.n - y;
-        index = x;
-    }
-
-    assert!(dir.is_some());
-    let dir = dir.unwrap();
-
-    let mut res = Vec::new();
-    for _ in 0..min_op {
-        res.push(Operation { dir, index });
-    }
-
-    // 動かした行（列）に福がいないなら元に戻さなくてよい
-    match dir {
-        Direction::Left | Direction::Right => {
-            if !board_state.exists_fuku_in_row(index, 0, board_state.n) {
-                return res;
-            }
-        }
-        Direction::Down | Direction::Up => {
-            if !board_state.exists_fuku_in_col(x, 0, board_state.n) {
-                return res;
-            }
-        }
-    }
-
-    // 動かした後の盤面において、全ての鬼が四方を福に囲まれていなければ元に戻さなくてよい
-    let mut after_board = board_state.clone();
-    for operation in &res {
-        after_board.apply(operation);
-    }
-
-    if !after_board.exists_oni_around_all_fuku() {
-        return res;
-    }
-
-    // 初期盤面に戻す
-    for _ in 0..min_op {
-        res.push(Operation {
-            dir: get_rev_dir(dir),
-            index,
-        })
-    }
-    res
+/// 乱択に使うシード。`--seed <u64>`、環境変数 `AHC042_SEED` の順に見て、どちらもなければ0
+fn parse_seed() -> u64 {
+    parse_arg("--seed").unwrap_or_else(|| match std::env::var("AHC042_SEED") {
+        Ok(seed) => seed.parse().unwrap_or_else(|err| {
+            eprintln!("AHC042_SEED: {}", err);
+            std::process::exit(1)
+        }),
+        Err(_) => 0,
+    })
 }
 
-fn main() {
-    let mut board_state = input_parser();
-    let mut ans = Vec::new();
-
-    while board_state.num_oni > 0 {
-        let (y, x) = find_oni(&board_state);
-        let operations = fall_oni_and_reset(y, x, &board_state);
-        for operation in operations {
-            board_state.apply(&operation);
-            ans.push(operation);
-            // eprintln!("op: {}", operation);
-        }
-        // eprintln!("num_oni: {}", board_state.num_oni);
-    }
-
-    for operation in ans {
-        println!("{}", operation);
+/// `--strategy`, `--opening`, `--order`, `--parking`, `--random-tie-break` で指定した設定。指定しなければデフォルト
+fn parse_config(seed: u64) -> Config {
+    let default = Config::default();
+    Config {
+        strategy: parse_arg("--strategy").unwrap_or(default.strategy),
+        order: parse_arg("--order").unwrap_or(default.order),
+        opening: has_flag("--opening"),
+        parking: has_flag("--parking"),
+        random_tie_break: has_flag("--random-tie-break"),
+        seed,
     }
 }
 
-#[cfg(test)]
-mod unittests {
-    use std::vec;
-
-    use super::*;
-
-    #[test]
-    fn test_generate_board_from_string() {
-        let board = generate_board_from_string(
-            3,
-            r"
-            x.o
-            o.x
-            .xo
-        ",
-        );
-
-        assert_eq!(
-            board,
-            BoardState {
-                n: 3,
-                board: vec![
-                    vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
-                    vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                    vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-                ],
-                num_oni: 3,
-                num_fuku: 3,
-            }
-        );
-    }
-
-    #[test]
-    fn test_generate_board_from_string_4x4() {
-        let board = generate_board_from_string(
-            4,
-            r"
-            x.ox
-            o.x.
-            .xo.
-            o.x.
-        ",
-        );
-
-        assert_eq!(
-            board,
-            BoardState {
-                n: 4,
-                board: vec![
-                    vec![
-                        RoomState::Oni,
-                        RoomState::Vacant,
-                        RoomState::Fuku,
-                        RoomState::Oni
-                    ],
-                    vec![
-                        RoomState::Fuku,
-                        RoomState::Vacant,
-                        RoomState::Oni,
-                        RoomState::Vacant
-                    ],
-                    vec![
-                        RoomState::Vacant,
-                        RoomState::Oni,
-                        RoomState::Fuku,
-                        RoomState::Vacant
-                    ],
-                    vec![
-                        RoomState::Fuku,
-                        RoomState::Vacant,
-                        RoomState::Oni,
-                        RoomState::Vacant
-                    ],
-                ],
-                num_oni: 5,
-                num_fuku: 4,
-            }
-        );
-    }
-
-    #[test]
-    fn test_apply_operation_left() {
-        let mut board_state = BoardState {
-            n: 3,
-            board: vec![
-                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ],
-            num_oni: 3,
-            num_fuku: 3,
-        };
-
-        board_state.apply(&Operation {
-            dir: Direction::Left,
-            index: 0,
-        });
-
-        assert_eq!(
-            board_state.board,
-            vec![
-                vec![RoomState::Vacant, RoomState::Fuku, RoomState::Vacant],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ]
+fn main() {
+    // SIGINTかSIGTERMを受け取ったら探索を打ち切り、それまでの一番良い解を出力する
+    interrupt::install_handler();
+    let board_state = input_parser();
+    let seed = parse_seed();
+    eprintln!("seed: {}", seed);
+    // `--time-limit <ms>` を付けると、その時間で解法を打ち切る。`mcts` はその時間まで探索し、
+    // `--portfolio` はその時間まで乱択の設定も試す
+    let deadline =
+        parse_arg::<u64>("--time-limit").map(|ms| Instant::now() + Duration::from_millis(ms));
+    let (config, ans) = if has_flag("--portfolio") {
+        // 正しい解がひとつもなければ（途中で打ち切られた場合も）貪欲法の解を出力する
+        solve_portfolio(&board_state, deadline, seed).unwrap_or_else(|| {
+            eprintln!("no valid answer, falling back to greedy");
+            greedy_fallback(&board_state, seed)
+        })
+    } else {
+        let config = parse_config(seed);
+        let mut solver = Solver::new(config.clone());
+        solver.deadline = deadline;
+        let ans = solver.solve(&board_state);
+        (config, ans)
+    };
+    eprintln!("config: {}", config);
+
+    let mut ans = expand(&ans);
+    // `--local-search <ms>` を付けると、得られた操作列をその時間だけ焼きなましで改善する
+    if let Some(ms) = parse_arg::<u64>("--local-search") {
+        let deadline = Instant::now() + Duration::from_millis(ms);
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let optimized = local_search::optimize(&board_state, &ans, deadline, &mut rng);
+        eprintln!(
+            "local search: score {} -> {}",
+            compute_score_of(&board_state, &ans),
+            compute_score_of(&board_state, &optimized)
         );
-        assert_eq!(board_state.num_oni, 2);
-        assert_eq!(board_state.num_fuku, 3);
+        ans = optimized;
     }
 
-    #[test]
-    fn test_apply_operation_right() {
-        let mut board_state = BoardState {
-            n: 3,
-            board: vec![
-                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ],
-            num_oni: 3,
-            num_fuku: 3,
-        };
-
-        board_state.apply(&Operation {
-            dir: Direction::Right,
-            index: 0,
-        });
-
-        assert_eq!(
-            board_state.board,
-            vec![
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Vacant],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ]
-        );
-        assert_eq!(board_state.num_oni, 3);
-        assert_eq!(board_state.num_fuku, 2);
+    // 全ての鬼を落とせていなかったり上限を超えていたりすれば、得点が一番高くなるように打ち切って仕上げる
+    let truncated = truncation::truncate(&board_state, &ans);
+    let (before, after) = (
+        compute_score_of(&board_state, &ans),
+        compute_score_of(&board_state, &truncated),
+    );
+    if after > before {
+        eprintln!("truncation: score {} -> {}", before, after);
+        ans = truncated;
     }
 
-    #[test]
-    fn test_apply_operation_up() {
-        let mut board_state = BoardState {
-            n: 3,
-            board: vec![
-                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ],
-            num_oni: 3,
-            num_fuku: 3,
-        };
-
-        board_state.apply(&Operation {
-            dir: Direction::Up,
-            index: 0,
-        });
-
-        assert_eq!(
-            board_state.board,
-            vec![
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Vacant, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ]
-        );
-        assert_eq!(board_state.num_oni, 2);
-        assert_eq!(board_state.num_fuku, 3);
+    if interrupt::is_interrupted() {
+        eprintln!("interrupted");
     }
-
-    #[test]
-    fn test_apply_operation_down() {
-        let mut board_state = BoardState {
-            n: 3,
-            board: vec![
-                vec![RoomState::Oni, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Oni],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Fuku],
-            ],
-            num_oni: 3,
-            num_fuku: 3,
-        };
-
-        board_state.apply(&Operation {
-            dir: Direction::Down,
-            index: 2,
-        });
-
-        assert_eq!(
-            board_state.board,
-            vec![
-                vec![RoomState::Oni, RoomState::Vacant, RoomState::Vacant],
-                vec![RoomState::Fuku, RoomState::Vacant, RoomState::Fuku],
-                vec![RoomState::Vacant, RoomState::Oni, RoomState::Oni],
-            ]
-        );
-        assert_eq!(board_state.num_oni, 3);
-        assert_eq!(board_state.num_fuku, 2);
+    for operation in ans {
+        println!("{}", operation);
     }
 }
@@ -0,0 +1,231 @@
+use std::time::Instant;
+
+use crate::board::*;
+use crate::parking::park_fuku;
+use crate::solver::{escapes, Solver};
+
+/// deadlineがないときの1体あたりの探索回数
+const ITERATIONS_PER_ONI: usize = 100;
+/// 各局面で試す行動の数の上限（操作回数が少ない順）
+const MAX_ACTIONS: usize = 8;
+
+/// 鬼を1体落とす行動の候補を操作回数が少ない順に返す
+///
+/// 鬼と落とす方向ごとに、落とした後に戻さない（全ての鬼を福を落とさずに落とせる状態が保たれる場合だけ）
+/// ものと、落とした分だけ戻すものの2つがある。戻さなければ今は安いが、動いた福が後で邪魔になることがある。
+fn actions(board_state: &BoardState) -> Vec<Vec<MacroOp>> {
+    let mut actions = Vec::new();
+    for (y, x) in board_state.oni_positions() {
+        for (dir, index, count) in escapes(y, x, board_state) {
+            let drop = MacroOp { dir, index, count };
+            let mut after_board = board_state.clone();
+            after_board.apply_macro(&drop);
+            if !after_board.exists_oni_around_all_fuku() {
+                actions.push(vec![drop]);
+            }
+            let restore = MacroOp {
+                dir: get_rev_dir(dir),
+                index,
+                count,
+            };
+            actions.push(vec![drop, restore]);
+        }
+    }
+    actions.sort_by_key(|action| num_operations(action));
+    actions.truncate(MAX_ACTIONS);
+    actions
+}
+
+struct Node {
+    board_state: BoardState,
+    /// 根からこのノードまでの行動
+    path: Vec<Vec<MacroOp>>,
+    /// 根からこのノードまでの操作回数
+    num_op: usize,
+    children: Vec<usize>,
+    /// まだ子ノードを作っていない行動（末尾から展開する）
+    untried: Vec<Vec<MacroOp>>,
+    visits: usize,
+    /// プレイアウトで得た (-操作回数) の和
+    total: f64,
+}
+
+impl Node {
+    fn new(board_state: BoardState, path: Vec<Vec<MacroOp>>, num_op: usize) -> Self {
+        let mut untried = actions(&board_state);
+        untried.reverse();
+        Self {
+            board_state,
+            path,
+            num_op,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            total: 0.0,
+        }
+    }
+}
+
+/// 盤面から鬼がいなくなるまで `Solver::next_oni` と `Solver::fall_oni` で落とし続けた行動の列
+fn playout(board_state: &BoardState, solver: &mut Solver) -> Vec<Vec<MacroOp>> {
+    let mut board_state = board_state.clone();
+    let mut res = Vec::new();
+    while board_state.num_oni > 0 {
+        let (y, x) = solver.next_oni(&board_state);
+        let action = solver.fall_oni(y, x, &board_state);
+        for macro_op in &action {
+            board_state.apply_macro(macro_op);
+        }
+        res.push(action);
+    }
+    res
+}
+
+fn total_operations(actions: &[Vec<MacroOp>]) -> usize {
+    actions.iter().map(|action| num_operations(action)).sum()
+}
+
+/// モンテカルロ木探索で鬼を1体ずつ落とす行動を決める
+///
+/// 行動は鬼を1体落とす操作列（戻すかどうかも選ぶ）で、プレイアウトは `Solver` の貪欲法、
+/// 報酬は全て落とし終えるまでの操作回数の少なさ（得点 8N^2 - T と同じ順序）。
+/// 1体落とすごとに、それまでに見つかった一番操作回数の少ない行動の列の最初の行動を確定させ、
+/// その続きを次の探索の初期解にするので、プレイアウトだけの貪欲法より悪くなることはない。
+/// solver.deadlineがあればそれまでの時間を残りの鬼の数で割って1体ずつに使い、なければ1体あたり一定回数探索する。
+/// SIGINTかSIGTERMを受け取ったら探索をやめ、それまでに見つかった行動の列をそのまま返す。
+/// config.parkingなら `solve_gather` と同じく1体落とす前に毎回 `park_fuku` で福を動かす。
+pub fn solve_mcts(board_state: &BoardState, solver: &mut Solver) -> Vec<MacroOp> {
+    // UCB1の探索の強さ（操作回数の単位）
+    let c = board_state.n as f64;
+    let mut board_state = board_state.clone();
+    let mut ans = Vec::new();
+    let mut best = playout(&board_state, solver);
+
+    while board_state.num_oni > 0 {
+        if solver.config.parking {
+            let parking = park_fuku(&mut board_state);
+            if !parking.is_empty() {
+                // 盤面が変わったので、それまでの行動の列は使えない
+                ans.extend(parking);
+                best = playout(&board_state, solver);
+            }
+            if board_state.num_oni == 0 {
+                break;
+            }
+        }
+        let step_deadline = solver.deadline.map(|deadline| {
+            let now = Instant::now();
+            now + deadline.saturating_duration_since(now) / board_state.num_oni as u32
+        });
+        let mut nodes = vec![Node::new(board_state.clone(), Vec::new(), 0)];
+        for iteration in 0.. {
+            match step_deadline {
+                _ if solver.interrupted() => break,
+                Some(deadline) if Instant::now() >= deadline => break,
+                None if iteration >= ITERATIONS_PER_ONI => break,
+                _ => (),
+            }
+
+            // 選択
+            let mut v = 0;
+            let mut visited = vec![0];
+            while nodes[v].untried.is_empty() && !nodes[v].children.is_empty() {
+                let ln = (nodes[v].visits as f64).ln();
+                v = *nodes[v]
+                    .children
+                    .iter()
+                    .max_by(|&&a, &&b| {
+                        let ucb = |u: usize| {
+                            nodes[u].total / nodes[u].visits as f64
+                                + c * (ln / nodes[u].visits as f64).sqrt()
+                        };
+                        ucb(a).partial_cmp(&ucb(b)).unwrap()
+                    })
+                    .unwrap();
+                visited.push(v);
+            }
+
+            // 展開
+            if let Some(action) = nodes[v].untried.pop() {
+                let mut after_board = nodes[v].board_state.clone();
+                for macro_op in &action {
+                    after_board.apply_macro(macro_op);
+                }
+                let mut path = nodes[v].path.clone();
+                let num_op = nodes[v].num_op + num_operations(&action);
+                path.push(action);
+                nodes.push(Node::new(after_board, path, num_op));
+                let u = nodes.len() - 1;
+                nodes[v].children.push(u);
+                v = u;
+                visited.push(v);
+            }
+
+            // プレイアウト
+            let rest = playout(&nodes[v].board_state, solver);
+            let num_op = nodes[v].num_op + total_operations(&rest);
+            if num_op < total_operations(&best) {
+                best = nodes[v].path.iter().cloned().chain(rest).collect();
+            }
+
+            // 逆伝播
+            for &u in &visited {
+                nodes[u].visits += 1;
+                nodes[u].total -= num_op as f64;
+            }
+        }
+
+        // 打ち切られたら一番良かった行動の列をそのまま使う
+        if solver.interrupted() {
+            ans.extend(best.into_iter().flatten());
+            break;
+        }
+        let action = best.remove(0);
+        for macro_op in &action {
+            board_state.apply_macro(macro_op);
+        }
+        ans.extend(action);
+    }
+
+    ans
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+    use crate::solver::{is_valid_answer, Config, Strategy};
+
+    #[test]
+    fn test_solve_mcts() {
+        let board_state = generate_board_from_string(
+            6,
+            r"
+            .x..x.
+            o..x..
+            .x.o.x
+            x...o.
+            ..x..o
+            .o..x.
+        ",
+        )
+        .unwrap();
+
+        let greedy = Solver::new(Config::default()).solve(&board_state);
+        let mcts = Solver::new(Config {
+            strategy: Strategy::Mcts,
+            ..Config::default()
+        })
+        .solve(&board_state);
+
+        assert!(is_valid_answer(&board_state, &mcts));
+        assert!(num_operations(&mcts) <= num_operations(&greedy));
+
+        let parking = Solver::new(Config {
+            strategy: Strategy::Mcts,
+            parking: true,
+            ..Config::default()
+        })
+        .solve(&board_state);
+        assert!(is_valid_answer(&board_state, &parking));
+    }
+}
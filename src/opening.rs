@@ -0,0 +1,96 @@
+use crate::board::*;
+
+/// 福のいない行（列）の鬼を全て落とすための (操作回数, 落とせる鬼の数, 操作) の候補を列挙する
+fn fuku_free_line_moves(board_state: &BoardState) -> Vec<(usize, usize, Operation)> {
+    let n = board_state.n;
+    let mut moves = Vec::new();
+    for index in 0..n {
+        let row = board_state.board[index].clone();
+        let col = (0..n).map(|i| board_state.board[i][index]).collect();
+        for (line, backward, forward) in [
+            (row, Direction::Left, Direction::Right),
+            (col, Direction::Up, Direction::Down),
+        ] {
+            if line.contains(&RoomState::Fuku) {
+                continue;
+            }
+            let oni = (0..n)
+                .filter(|&j| line[j] == RoomState::Oni)
+                .collect::<Vec<_>>();
+            let (Some(&first), Some(&last)) = (oni.first(), oni.last()) else {
+                continue;
+            };
+            moves.push((
+                last + 1,
+                oni.len(),
+                Operation {
+                    dir: backward,
+                    index,
+                },
+            ));
+            moves.push((
+                n - first,
+                oni.len(),
+                Operation {
+                    dir: forward,
+                    index,
+                },
+            ));
+        }
+    }
+    moves
+}
+
+/// 福のいない行（列）にいる鬼を全て落とす操作列を返し、board_stateにも適用する
+///
+/// 福のいない行（列）は動かしても福が落ちないので、盤面を戻す必要がない。
+/// 1体あたりの操作回数が一番少ない行（列）と向きから順に落とし、毎回数え直す。
+/// 交差する行と列の両方が空いている鬼はどちらか一方で落ちるので、後の行（列）ほど安くなることがある。
+pub fn clear_fuku_free_lines(board_state: &mut BoardState) -> Vec<MacroOp> {
+    let mut res = Vec::new();
+    loop {
+        let moves = fuku_free_line_moves(board_state);
+        let Some(&(count, _, operation)) = moves
+            .iter()
+            .min_by(|a, b| (a.0 * b.1).cmp(&(b.0 * a.1)).then(a.0.cmp(&b.0)))
+        else {
+            break;
+        };
+        let macro_op = MacroOp::new(operation, count);
+        board_state.apply_macro(&macro_op);
+        res.push(macro_op);
+    }
+    res
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_clear_fuku_free_lines() {
+        let mut board_state = generate_board_from_string(
+            4,
+            r"
+            x..x
+            .o..
+            x.o.
+            ..x.
+        ",
+        )
+        .unwrap();
+
+        let operations = clear_fuku_free_lines(&mut board_state);
+
+        // 0行目は最初は4回かかるが、3列目から (0, 3) を落とした後は1回で済む
+        assert_eq!(
+            expand(&operations)
+                .iter()
+                .map(|operation| operation.to_string())
+                .collect::<Vec<_>>(),
+            vec!["U 3", "L 0", "D 0", "D 0", "R 3", "R 3"]
+        );
+        assert_eq!(board_state.num_oni, 0);
+        assert_eq!(board_state.num_fuku, 2);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::board::*;
+use crate::solver::escapes;
+
+/// 全ての鬼について、福を落とさずに落とせる一番近い端までの操作回数の和
+///
+/// 落とせない鬼がいればNone。福が鬼の逃げ道を塞いでいるほど大きくなる。
+pub fn escape_potential(board_state: &BoardState) -> Option<usize> {
+    board_state
+        .oni_positions()
+        .into_iter()
+        .map(|(y, x)| escapes(y, x, board_state).iter().map(|e| e.2).min())
+        .sum()
+}
+
+/// 福のいる行（列）を福が落ちない範囲で動かす操作を全て列挙する
+fn parking_moves(board_state: &BoardState) -> Vec<MacroOp> {
+    let n = board_state.n;
+    let mut moves = Vec::new();
+    for index in 0..n {
+        let row = board_state.board[index].clone();
+        let col = (0..n)
+            .map(|i| board_state.board[i][index])
+            .collect::<Vec<_>>();
+        for (line, backward, forward) in [
+            (row, Direction::Left, Direction::Right),
+            (col, Direction::Up, Direction::Down),
+        ] {
+            let fuku = (0..n)
+                .filter(|&j| line[j] == RoomState::Fuku)
+                .collect::<Vec<_>>();
+            let (Some(&first), Some(&last)) = (fuku.first(), fuku.last()) else {
+                continue;
+            };
+            for count in 1..=first {
+                moves.push(MacroOp {
+                    dir: backward,
+                    index,
+                    count,
+                });
+            }
+            for count in 1..n - last {
+                moves.push(MacroOp {
+                    dir: forward,
+                    index,
+                    count,
+                });
+            }
+        }
+    }
+    moves
+}
+
+/// 福を動かして鬼の逃げ道を空ける操作列を返し、board_stateにも適用する
+///
+/// 福のいる行（列）を福が落ちない範囲で動かす操作のうち、`escape_potential` の減り方から
+/// 操作回数を引いた値が一番大きいものを、正になる限り繰り返す。盤面は元に戻さないが、
+/// 全ての鬼が福を落とさずに落とせる状態は保つ。
+pub fn park_fuku(board_state: &mut BoardState) -> Vec<MacroOp> {
+    let mut res = Vec::new();
+    let Some(mut potential) = escape_potential(board_state) else {
+        return res;
+    };
+    loop {
+        let best = parking_moves(board_state)
+            .into_iter()
+            .filter_map(|macro_op| {
+                let mut after_board = board_state.clone();
+                after_board.apply_macro(&macro_op);
+                let after = escape_potential(&after_board)?;
+                let gain = potential as i64 - after as i64 - macro_op.count as i64;
+                Some((gain, macro_op, after_board, after))
+            })
+            .max_by_key(|c| c.0);
+        match best {
+            Some((gain, macro_op, after_board, after)) if gain > 0 => {
+                *board_state = after_board;
+                potential = after;
+                res.push(macro_op);
+            }
+            _ => break,
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_park_fuku() {
+        let mut board_state = generate_board_from_string(
+            6,
+            r"
+            ....o.
+            ....xo
+            ...x..
+            ..xo.x
+            ..oo..
+            .o....
+        ",
+        )
+        .unwrap();
+
+        // (1, 4) の鬼は右を (1, 5) の福に、上を (0, 4) の福に塞がれていて5回かかるが、
+        // 5列目を下に1回動かすと右から2回で落とせるようになる
+        let macro_ops = park_fuku(&mut board_state);
+
+        assert_eq!(
+            expand(&macro_ops)
+                .iter()
+                .map(|operation| operation.to_string())
+                .collect::<Vec<_>>(),
+            vec!["D 5"]
+        );
+        assert_eq!(board_state.num_fuku, 6);
+        assert_eq!(board_state.num_oni, 4);
+        assert_eq!(board_state.escape_directions(1, 4).len(), 3);
+        assert!(!board_state.exists_oni_around_all_fuku());
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::board::*;
+use crate::interrupt::is_interrupted;
+use crate::solver::{is_valid_answer, Config, RemovalOrder, Solver, Strategy};
+
+/// 乱択を使わない設定を全て返す（遅い `Lookahead` は含めない）
+pub fn deterministic_configs() -> Vec<Config> {
+    let mut configs = Vec::new();
+    for strategy in Strategy::ALL {
+        for opening in [false, true] {
+            for order in [
+                RemovalOrder::RowMajor,
+                RemovalOrder::ColumnMajor,
+                RemovalOrder::Cheapest,
+            ] {
+                configs.push(Config {
+                    strategy,
+                    opening,
+                    order,
+                    ..Config::default()
+                });
+            }
+        }
+    }
+    configs
+}
+
+/// i番目の乱択を使う設定（解法・序盤・福の移動・鬼の選び方を順に回し、シードはseed + i）
+pub fn randomized_config(i: usize, seed: u64) -> Config {
+    let strategies = Strategy::ALL.len();
+    Config {
+        strategy: Strategy::ALL[i % strategies],
+        opening: (i / strategies) % 2 == 1,
+        parking: (i / strategies / 2) % 2 == 1,
+        order: RemovalOrder::ALL[(i / strategies / 4) % RemovalOrder::ALL.len()],
+        random_tie_break: true,
+        seed: seed.wrapping_add(i as u64),
+    }
+}
+
+/// 複数の設定を全てのCPUコアで並列に解き、一番操作回数が少ない正しい解とその設定を返す
+///
+/// deadlineがなければ `deterministic_configs` を全て試す。deadlineがあれば `deterministic_configs` の後に
+/// `randomized_config` を順に試し、deadlineを過ぎたら新しい設定は試さない。それぞれの解法にもdeadlineを渡すので、
+/// deadlineを過ぎて解き終わらなかった設定の解は使わない。
+/// 操作回数が同じなら先に並んでいる設定の解を選ぶので、deadlineがなければ結果は実行ごとに変わらない。
+/// SIGINTかSIGTERMを受け取ったら新しい設定は試さず、それまでの解から選ぶ。
+pub fn solve_portfolio(
+    board_state: &BoardState,
+    deadline: Option<Instant>,
+    seed: u64,
+) -> Option<(Config, Vec<MacroOp>)> {
+    let configs = deterministic_configs();
+    let next = AtomicUsize::new(0);
+    let config_at = |i: usize| match configs.get(i) {
+        Some(config) => config.clone(),
+        None => randomized_config(i - configs.len(), seed),
+    };
+    // (設定の番号, 解)
+    let best: Mutex<Option<(usize, Vec<MacroOp>)>> = Mutex::new(None);
+    let num_threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    std::thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if is_interrupted()
+                    || deadline.map_or(i >= configs.len(), |deadline| Instant::now() >= deadline)
+                {
+                    break;
+                }
+                let mut solver = Solver::new(config_at(i));
+                solver.deadline = deadline;
+                let ans = solver.solve(board_state);
+                if !is_valid_answer(board_state, &ans) {
+                    continue;
+                }
+                let mut best = best.lock().unwrap();
+                if best.as_ref().map_or(true, |(j, best_ans)| {
+                    (num_operations(&ans), i) < (num_operations(best_ans), *j)
+                }) {
+                    *best = Some((i, ans));
+                }
+            });
+        }
+    });
+
+    best.into_inner()
+        .unwrap()
+        .map(|(i, ans)| (config_at(i), ans))
+}
+
+/// `solve_portfolio` で正しい解がひとつもなかったときに使う貪欲法の解
+///
+/// 打ち切られて正しい解がなかった場合にも使うので、SIGINTやSIGTERMを受け取った後でも最後まで解く。
+pub fn greedy_fallback(board_state: &BoardState, seed: u64) -> (Config, Vec<MacroOp>) {
+    let config = Config {
+        seed,
+        ..Config::default()
+    };
+    let mut solver = Solver::new(config.clone());
+    solver.interruptible = false;
+    (config, solver.solve(board_state))
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_solve_portfolio() {
+        let board_state = generate_board_from_string(
+            6,
+            r"
+            .x..x.
+            o..x..
+            .x.o.x
+            x...o.
+            ..x..o
+            .o..x.
+        ",
+        )
+        .unwrap();
+
+        let (config, ans) = solve_portfolio(&board_state, None, 0).unwrap();
+
+        assert!(is_valid_answer(&board_state, &ans));
+        for config in deterministic_configs() {
+            assert!(
+                num_operations(&Solver::new(config).solve(&board_state)) >= num_operations(&ans)
+            );
+        }
+        assert_eq!(
+            num_operations(&Solver::new(config).solve(&board_state)),
+            num_operations(&ans)
+        );
+    }
+
+    #[test]
+    fn test_randomized_config_reproducible() {
+        let board_state = generate_board_from_string(
+            6,
+            r"
+            .x..x.
+            o..x..
+            .x.o.x
+            x...o.
+            ..x..o
+            .o..x.
+        ",
+        )
+        .unwrap();
+
+        // 同じシードなら乱択を使っても同じ操作列になる
+        for i in 0..12 {
+            let solve = |seed| {
+                expand(&Solver::new(randomized_config(i, seed)).solve(&board_state))
+                    .iter()
+                    .map(|operation| operation.to_string())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(solve(42), solve(42));
+        }
+    }
+}
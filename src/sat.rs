@@ -0,0 +1,322 @@
+use crate::board::*;
+
+/// 「福を落とさずにT回以内の操作で全ての鬼を落とせるか」のSATへの符号化
+///
+/// 変数は各時刻 t (0..=T) の各マスに鬼・福がいるか、各時刻 t (0..T) にどの操作をするか
+/// （4N通りと何もしない操作のちょうど1つ）。何もしない操作は末尾にまとめる。
+pub struct SatEncoding {
+    pub n: usize,
+    pub horizon: usize,
+}
+
+impl SatEncoding {
+    pub fn new(n: usize, horizon: usize) -> Self {
+        Self { n, horizon }
+    }
+
+    /// 1時刻あたりの操作の変数の数（何もしない操作を含む）
+    fn num_actions(&self) -> usize {
+        4 * self.n + 1
+    }
+
+    fn num_state_vars(&self) -> usize {
+        (self.horizon + 1) * self.n * self.n * 2
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_state_vars() + self.horizon * self.num_actions()
+    }
+
+    /// 時刻tに(i, j)に鬼がいる
+    fn oni(&self, t: usize, i: usize, j: usize) -> i32 {
+        (((t * self.n + i) * self.n + j) * 2 + 1) as i32
+    }
+
+    /// 時刻tに(i, j)に福がいる
+    fn fuku(&self, t: usize, i: usize, j: usize) -> i32 {
+        self.oni(t, i, j) + 1
+    }
+
+    /// 時刻tにk番目の操作をする（k = 方向 * N + 行（列）、k = 4Nは何もしない）
+    fn action(&self, t: usize, k: usize) -> i32 {
+        (self.num_state_vars() + t * self.num_actions() + k + 1) as i32
+    }
+
+    fn operation(&self, k: usize) -> Operation {
+        Operation {
+            dir: Direction::ALL[k / self.n],
+            index: k % self.n,
+        }
+    }
+
+    /// 操作で動く行（列）のマスを、駒が落ちる端から順に並べたもの
+    fn line(&self, operation: &Operation) -> Vec<(usize, usize)> {
+        let n = self.n;
+        let p = operation.index;
+        match operation.dir {
+            Direction::Left => (0..n).map(|j| (p, j)).collect(),
+            Direction::Right => (0..n).rev().map(|j| (p, j)).collect(),
+            Direction::Up => (0..n).map(|i| (i, p)).collect(),
+            Direction::Down => (0..n).rev().map(|i| (i, p)).collect(),
+        }
+    }
+
+    /// 節の列。リテラルは変数番号（1始まり）で、負なら否定
+    pub fn clauses(&self, board_state: &BoardState) -> Vec<Vec<i32>> {
+        let n = self.n;
+        let na = self.num_actions();
+        let mut clauses = Vec::new();
+
+        // 初期盤面
+        for i in 0..n {
+            for j in 0..n {
+                let (oni, fuku) = match board_state.board[i][j] {
+                    RoomState::Oni => (true, false),
+                    RoomState::Fuku => (false, true),
+                    RoomState::Vacant => (false, false),
+                };
+                clauses.push(vec![if oni { 1 } else { -1 } * self.oni(0, i, j)]);
+                clauses.push(vec![if fuku { 1 } else { -1 } * self.fuku(0, i, j)]);
+            }
+        }
+
+        for t in 0..self.horizon {
+            // 操作はちょうど1つ
+            clauses.push((0..na).map(|k| self.action(t, k)).collect());
+            for k in 0..na {
+                for l in k + 1..na {
+                    clauses.push(vec![-self.action(t, k), -self.action(t, l)]);
+                }
+            }
+            // 何もしない操作の後は何もしない
+            if t + 1 < self.horizon {
+                clauses.push(vec![-self.action(t, 4 * n), self.action(t + 1, 4 * n)]);
+            }
+
+            // 行と列のどちらも動かなければマスはそのまま
+            for i in 0..n {
+                for j in 0..n {
+                    let touching = [i, n + i, 2 * n + j, 3 * n + j].map(|k| self.action(t, k));
+                    for var in [Self::oni, Self::fuku] {
+                        let (before, after) = (var(self, t, i, j), var(self, t + 1, i, j));
+                        let mut clause = vec![-before, after];
+                        clause.extend(touching);
+                        clauses.push(clause);
+                        let mut clause = vec![before, -after];
+                        clause.extend(touching);
+                        clauses.push(clause);
+                    }
+                }
+            }
+
+            // 動かした行（列）は1マスずれ、端の駒は落ちる
+            for k in 0..4 * n {
+                let a = self.action(t, k);
+                let line = self.line(&self.operation(k));
+                let (fi, fj) = line[0];
+                clauses.push(vec![-a, -self.fuku(t, fi, fj)]);
+                for var in [Self::oni, Self::fuku] {
+                    for m in 0..n {
+                        let (i, j) = line[m];
+                        let after = var(self, t + 1, i, j);
+                        if m + 1 < n {
+                            let (si, sj) = line[m + 1];
+                            let before = var(self, t, si, sj);
+                            clauses.push(vec![-a, -before, after]);
+                            clauses.push(vec![-a, before, -after]);
+                        } else {
+                            clauses.push(vec![-a, -after]);
+                        }
+                    }
+                }
+            }
+        }
+
+        // 最後に鬼は残らない
+        for i in 0..n {
+            for j in 0..n {
+                clauses.push(vec![-self.oni(self.horizon, i, j)]);
+            }
+        }
+        clauses
+    }
+
+    /// DIMACS CNF形式の文字列
+    pub fn to_dimacs(&self, board_state: &BoardState) -> String {
+        let clauses = self.clauses(board_state);
+        let mut res = format!(
+            "c ahc042 n={} horizon={}\np cnf {} {}\n",
+            self.n,
+            self.horizon,
+            self.num_vars(),
+            clauses.len()
+        );
+        for clause in &clauses {
+            for lit in clause {
+                res += &format!("{} ", lit);
+            }
+            res += "0\n";
+        }
+        res
+    }
+
+    /// SATソルバの出力から操作列を復元する。充足不能ならNone
+    ///
+    /// SAT competition形式（`s SATISFIABLE` と `v ...` の行）と、minisatの結果ファイル
+    /// （`SAT` の次の行に割り当て）の両方を読める。
+    pub fn decode(&self, solver_output: &str) -> Result<Option<Vec<Operation>>, String> {
+        let mut satisfiable = None;
+        let mut assignment = vec![false; self.num_vars() + 1];
+        let mut has_values = false;
+        for line in solver_output.lines() {
+            let line = line.trim();
+            let values = match line {
+                "" => continue,
+                "s SATISFIABLE" | "SAT" => {
+                    satisfiable = Some(true);
+                    continue;
+                }
+                "s UNSATISFIABLE" | "UNSAT" => {
+                    satisfiable = Some(false);
+                    continue;
+                }
+                _ if line.starts_with('c') || line.starts_with('s') => continue,
+                _ => line.strip_prefix('v').unwrap_or(line),
+            };
+            for token in values.split_whitespace() {
+                let lit = token
+                    .parse::<i64>()
+                    .map_err(|_| format!("invalid literal: {}", token))?;
+                let var = lit.unsigned_abs() as usize;
+                if var > self.num_vars() {
+                    return Err(format!("variable out of range: {}", lit));
+                }
+                assignment[var] = lit > 0;
+                has_values = true;
+            }
+        }
+        match satisfiable {
+            Some(false) => return Ok(None),
+            None if !has_values => return Err("no assignment in solver output".to_owned()),
+            _ => (),
+        }
+
+        let mut res = Vec::new();
+        for t in 0..self.horizon {
+            let chosen = (0..self.num_actions())
+                .filter(|&k| assignment[self.action(t, k) as usize])
+                .collect::<Vec<_>>();
+            if chosen.len() != 1 {
+                return Err(format!("{} operations at step {}", chosen.len(), t));
+            }
+            if chosen[0] == 4 * self.n {
+                break;
+            }
+            res.push(self.operation(chosen[0]));
+        }
+        Ok(Some(res))
+    }
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    /// 操作列を実際に動かして全ての変数の値を決める
+    fn assignment(
+        encoding: &SatEncoding,
+        board_state: &BoardState,
+        ans: &[Operation],
+    ) -> Vec<bool> {
+        let n = encoding.n;
+        let mut assignment = vec![false; encoding.num_vars() + 1];
+        let mut board_state = board_state.clone();
+        for t in 0..=encoding.horizon {
+            for i in 0..n {
+                for j in 0..n {
+                    assignment[encoding.oni(t, i, j) as usize] =
+                        board_state.board[i][j] == RoomState::Oni;
+                    assignment[encoding.fuku(t, i, j) as usize] =
+                        board_state.board[i][j] == RoomState::Fuku;
+                }
+            }
+            if t == encoding.horizon {
+                break;
+            }
+            let k = match ans.get(t) {
+                Some(operation) => {
+                    board_state.apply(operation);
+                    operation.dir as usize * n + operation.index
+                }
+                None => 4 * n,
+            };
+            assignment[encoding.action(t, k) as usize] = true;
+        }
+        assignment
+    }
+
+    fn satisfies(clauses: &[Vec<i32>], assignment: &[bool]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|&lit| assignment[lit.unsigned_abs() as usize] == (lit > 0))
+        })
+    }
+
+    #[test]
+    fn test_sat_encoding() {
+        let board_state = generate_board_from_string(
+            3,
+            r"
+            ...
+            x.o
+            .xo
+        ",
+        )
+        .unwrap();
+        let ans = [
+            Operation {
+                dir: Direction::Left,
+                index: 1,
+            },
+            Operation {
+                dir: Direction::Down,
+                index: 1,
+            },
+        ];
+        let encoding = SatEncoding::new(3, 4);
+        let clauses = encoding.clauses(&board_state);
+
+        let valid = assignment(&encoding, &board_state, &ans);
+        assert!(satisfies(&clauses, &valid));
+        // 福を落とす操作列と、鬼が残る操作列は充足しない
+        let drop_fuku = assignment(&encoding, &board_state, &[ans[0], ans[0], ans[0], ans[1]]);
+        assert!(!satisfies(&clauses, &drop_fuku));
+        let oni_left = assignment(&encoding, &board_state, &ans[..1]);
+        assert!(!satisfies(&clauses, &oni_left));
+
+        let output = (1..valid.len())
+            .map(|var| {
+                format!(
+                    "{}",
+                    if valid[var] {
+                        var as i64
+                    } else {
+                        -(var as i64)
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let decoded = encoding
+            .decode(&format!("s SATISFIABLE\nv {} 0\n", output))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            decoded.iter().map(|o| o.to_string()).collect::<Vec<_>>(),
+            vec!["L 1", "D 1"]
+        );
+        assert!(encoding.decode("s UNSATISFIABLE\n").unwrap().is_none());
+        assert!(encoding.decode("").is_err());
+    }
+}
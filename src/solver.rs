@@ -0,0 +1,506 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+use crate::board::*;
+use crate::gather::solve_gather;
+use crate::interrupt::is_interrupted;
+use crate::mcts::solve_mcts;
+use crate::opening::clear_fuku_free_lines;
+use crate::parking::park_fuku;
+
+/// 解法の種類
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    /// 鬼を1体ずつ落としては盤面を戻す
+    Greedy,
+    /// 鬼を1つの行（列）に寄せてからまとめて掃き出す
+    Gather,
+    /// 鬼を1体落とす行動をモンテカルロ木探索で選ぶ
+    Mcts,
+}
+
+impl Strategy {
+    /// `Mcts` は時間を使い切るので含めない
+    pub const ALL: [Strategy; 2] = [Strategy::Greedy, Strategy::Gather];
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "greedy" => Ok(Strategy::Greedy),
+            "gather" => Ok(Strategy::Gather),
+            "mcts" => Ok(Strategy::Mcts),
+            _ => Err(format!("unknown strategy: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Greedy => write!(f, "greedy"),
+            Strategy::Gather => write!(f, "gather"),
+            Strategy::Mcts => write!(f, "mcts"),
+        }
+    }
+}
+
+/// 次に落とす鬼の選び方
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RemovalOrder {
+    /// 上の行から、同じ行なら左から
+    RowMajor,
+    /// 左の列から、同じ列なら上から
+    ColumnMajor,
+    /// 残っている鬼から一様ランダム
+    Random,
+    /// 落として必要なら戻すまでの操作回数が一番少ない鬼から
+    Cheapest,
+    /// 最初の1体を落とした後に `Cheapest` で続けてk体目まで落とす操作回数が一番少ない鬼から
+    Lookahead(usize),
+}
+
+impl RemovalOrder {
+    /// `Lookahead` は遅いので含めない
+    pub const ALL: [RemovalOrder; 4] = [
+        RemovalOrder::RowMajor,
+        RemovalOrder::ColumnMajor,
+        RemovalOrder::Random,
+        RemovalOrder::Cheapest,
+    ];
+}
+
+impl FromStr for RemovalOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "row-major" => Ok(RemovalOrder::RowMajor),
+            "column-major" => Ok(RemovalOrder::ColumnMajor),
+            "random" => Ok(RemovalOrder::Random),
+            "cheapest" => Ok(RemovalOrder::Cheapest),
+            _ => match s.strip_prefix("lookahead-").map(|k| k.parse()) {
+                Some(Ok(k)) if k > 0 => Ok(RemovalOrder::Lookahead(k)),
+                _ => Err(format!("unknown removal order: {}", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RemovalOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemovalOrder::RowMajor => write!(f, "row-major"),
+            RemovalOrder::ColumnMajor => write!(f, "column-major"),
+            RemovalOrder::Random => write!(f, "random"),
+            RemovalOrder::Cheapest => write!(f, "cheapest"),
+            RemovalOrder::Lookahead(k) => write!(f, "lookahead-{}", k),
+        }
+    }
+}
+
+/// 解法の設定
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub strategy: Strategy,
+    /// 序盤に福のいない行（列）の鬼を先に全て落とすか
+    pub opening: bool,
+    pub order: RemovalOrder,
+    /// 鬼を1体落とす前に毎回、福を動かして鬼の逃げ道を空けるか
+    pub parking: bool,
+    /// 同じ操作回数で落とせる方向が複数あるときにランダムに選ぶか（falseなら L, R, U, D の順で先のもの）
+    pub random_tie_break: bool,
+    /// 乱択に使うシード
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Greedy,
+            opening: false,
+            order: RemovalOrder::RowMajor,
+            parking: false,
+            random_tie_break: false,
+            seed: 0,
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "strategy={} opening={} order={} parking={} random_tie_break={} seed={}",
+            self.strategy, self.opening, self.order, self.parking, self.random_tie_break, self.seed
+        )
+    }
+}
+
+/// 設定と乱数生成器を持って盤面を解く
+pub struct Solver {
+    pub config: Config,
+    /// いつまでに解き終えるか。`mcts` はそれまでの時間を使って探索し、
+    /// `greedy` と `gather` は過ぎたら鬼が残っていてもそこまでの操作列を返す
+    pub deadline: Option<Instant>,
+    /// falseならSIGINTやSIGTERMを受け取った後でも打ち切らずに最後まで解く
+    pub interruptible: bool,
+    rng: ChaCha20Rng,
+}
+
+impl Solver {
+    pub fn new(config: Config) -> Self {
+        let rng = ChaCha20Rng::seed_from_u64(config.seed);
+        Self {
+            config,
+            deadline: None,
+            interruptible: true,
+            rng,
+        }
+    }
+
+    /// SIGINTかSIGTERMを受け取っていて、探索を打ち切るべきか
+    pub fn interrupted(&self) -> bool {
+        self.interruptible && is_interrupted()
+    }
+
+    /// 打ち切られたか、deadlineを過ぎたか
+    pub fn should_stop(&self) -> bool {
+        self.interrupted()
+            || self
+                .deadline
+                .map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    pub fn solve(&mut self, board_state: &BoardState) -> Vec<MacroOp> {
+        let mut board_state = board_state.clone();
+        let mut ans = Vec::new();
+        if self.config.opening {
+            ans.extend(clear_fuku_free_lines(&mut board_state));
+        }
+        match self.config.strategy {
+            Strategy::Greedy => ans.extend(self.solve_greedy(&board_state)),
+            Strategy::Gather => ans.extend(solve_gather(&board_state, self)),
+            Strategy::Mcts => ans.extend(solve_mcts(&board_state, self)),
+        }
+        ans
+    }
+
+    /// 次に落とす鬼の位置
+    pub fn next_oni(&mut self, board_state: &BoardState) -> (usize, usize) {
+        match self.config.order {
+            RemovalOrder::RowMajor => find_oni(board_state),
+            RemovalOrder::ColumnMajor => {
+                let (x, y) = find_oni(&board_state.transposed());
+                (y, x)
+            }
+            RemovalOrder::Random => *board_state.oni_positions().choose(&mut self.rng).unwrap(),
+            RemovalOrder::Cheapest => cheapest_removal(board_state).unwrap().0,
+            RemovalOrder::Lookahead(k) => lookahead_removal(board_state, k).unwrap(),
+        }
+    }
+
+    /// (y, x) にいる鬼を落とし、必要なら初期盤面に戻す操作列のうち、一番操作回数が少ないものを返す
+    pub fn fall_oni(&mut self, y: usize, x: usize, board_state: &BoardState) -> Vec<MacroOp> {
+        if !self.config.random_tie_break {
+            return fall_oni_and_reset(y, x, board_state);
+        }
+        let candidates = fall_candidates(y, x, board_state);
+        let min_op = candidates.iter().map(|c| num_operations(c)).min().unwrap();
+        let cheapest = candidates
+            .into_iter()
+            .filter(|c| num_operations(c) == min_op)
+            .collect::<Vec<_>>();
+        cheapest.choose(&mut self.rng).unwrap().clone()
+    }
+
+    /// 盤面から鬼がいなくなるまで、鬼を1体落として戻す操作を繰り返した操作列を返す
+    ///
+    /// SIGINTかSIGTERMを受け取るかdeadlineを過ぎたら、鬼が残っていてもそこまでの操作列を返す。
+    fn solve_greedy(&mut self, board_state: &BoardState) -> Vec<MacroOp> {
+        let mut board_state = board_state.clone();
+        let mut ans = Vec::new();
+
+        while board_state.num_oni > 0 {
+            // 打ち切られたらそこまでの操作列を返す
+            if self.should_stop() {
+                break;
+            }
+            if self.config.parking {
+                ans.extend(park_fuku(&mut board_state));
+                if board_state.num_oni == 0 {
+                    break;
+                }
+            }
+            let (y, x) = self.next_oni(&board_state);
+            let operations = self.fall_oni(y, x, &board_state);
+            for macro_op in operations {
+                board_state.apply_macro(&macro_op);
+                ans.push(macro_op);
+            }
+        }
+
+        ans
+    }
+}
+
+pub fn find_oni(board_state: &BoardState) -> (usize, usize) {
+    assert!(board_state.num_oni > 0);
+    for i in 0..board_state.n {
+        for j in 0..board_state.n {
+            if board_state.board[i][j] == RoomState::Oni {
+                return (i, j);
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// 鬼を落とす操作列とその後の盤面の評価（小さいほど良い）
+///
+/// 操作回数が同じなら、福のいない行と列が多く、後の鬼を戻さずに落としやすい盤面を優先する。
+fn removal_key(macro_ops: &[MacroOp], after_board: &BoardState) -> (usize, Reverse<usize>) {
+    (
+        num_operations(macro_ops),
+        Reverse(after_board.num_fuku_free_lines()),
+    )
+}
+
+/// 残っている鬼それぞれについて、(位置, `fall_oni_and_reset` の操作列, 落とした後の盤面) を行優先順に返す
+fn removals(board_state: &BoardState) -> Vec<((usize, usize), Vec<MacroOp>, BoardState)> {
+    board_state
+        .oni_positions()
+        .into_iter()
+        .filter(|&(y, x)| !board_state.escape_directions(y, x).is_empty())
+        .map(|(y, x)| {
+            let macro_ops = fall_oni_and_reset(y, x, board_state);
+            let mut after_board = board_state.clone();
+            for macro_op in &macro_ops {
+                after_board.apply_macro(macro_op);
+            }
+            ((y, x), macro_ops, after_board)
+        })
+        .collect()
+}
+
+/// 一番安く落とせる鬼の (位置, 操作列, 落とした後の盤面)
+///
+/// 同じ評価なら行優先順で先の鬼を選ぶ。落とせる鬼がいなければNone。
+pub fn cheapest_removal(
+    board_state: &BoardState,
+) -> Option<((usize, usize), Vec<MacroOp>, BoardState)> {
+    removals(board_state)
+        .into_iter()
+        .min_by_key(|(_, macro_ops, after_board)| removal_key(macro_ops, after_board))
+}
+
+/// 最初にどの鬼を落とすかを全て試し、その後 `cheapest_removal` でk体目まで落としたときの
+/// 合計の操作回数が一番少ない最初の鬼の位置
+pub fn lookahead_removal(board_state: &BoardState, k: usize) -> Option<(usize, usize)> {
+    removals(board_state)
+        .into_iter()
+        .min_by_key(|(_, macro_ops, after_board)| {
+            let (num_op, fuku_free_lines) = removal_key(macro_ops, after_board);
+            let mut total = num_op;
+            let mut board_state = after_board.clone();
+            for _ in 1..k {
+                if board_state.num_oni == 0 {
+                    break;
+                }
+                let Some((_, macro_ops, next_board)) = cheapest_removal(&board_state) else {
+                    // 落とせない鬼が残るなら選ばない
+                    total = usize::MAX;
+                    break;
+                };
+                total += num_operations(&macro_ops);
+                board_state = next_board;
+            }
+            (total, fuku_free_lines)
+        })
+        .map(|(pos, _, _)| pos)
+}
+
+/// (y, x) にいる鬼を福を落とさずに落とせる (方向, 動かす行（列）, 操作回数) を L, R, U, D の順に全て返す
+pub fn escapes(y: usize, x: usize, board_state: &BoardState) -> Vec<(Direction, usize, usize)> {
+    let n = board_state.n;
+    board_state
+        .escape_directions(y, x)
+        .into_iter()
+        .map(|dir| match dir {
+            Direction::Left => (dir, y, x + 1),
+            Direction::Right => (dir, y, n - x),
+            Direction::Up => (dir, x, y + 1),
+            Direction::Down => (dir, x, n - y),
+        })
+        .collect()
+}
+
+/// (y, x) にいる鬼を福を落とさずに落とせる一番近い端への (方向, 動かす行（列）, 操作回数) を返す
+///
+/// 同じ操作回数の方向が複数あるときは L, R, U, D の順で先のものを選ぶ
+pub fn nearest_escape(
+    y: usize,
+    x: usize,
+    board_state: &BoardState,
+) -> Option<(Direction, usize, usize)> {
+    escapes(y, x, board_state).into_iter().min_by_key(|e| e.2)
+}
+
+/// (y, x) にいる鬼を落とせる方向ごとに、落としてから必要なら初期盤面に戻す操作列を L, R, U, D の順に返す
+pub fn fall_candidates(y: usize, x: usize, board_state: &BoardState) -> Vec<Vec<MacroOp>> {
+    escapes(y, x, board_state)
+        .into_iter()
+        .map(|escape| fall_oni_toward(y, x, escape, board_state))
+        .collect()
+}
+
+/// (y, x) にいる鬼を落としてから必要なら初期盤面に戻すという操作列を返す
+///
+/// 戻す分も含めた操作回数が一番少ない方向を選ぶ。一番近い端より遠くても、福のいない行（列）や
+/// 動かしても他の鬼が囲まれない方向なら戻さなくてよいので安くなることがある。
+/// 同じ操作回数なら L, R, U, D の順で先のものを選ぶ。
+pub fn fall_oni_and_reset(y: usize, x: usize, board_state: &BoardState) -> Vec<MacroOp> {
+    let candidates = fall_candidates(y, x, board_state);
+    assert!(!candidates.is_empty());
+    candidates
+        .into_iter()
+        .min_by_key(|c| num_operations(c))
+        .unwrap()
+}
+
+/// (y, x) にいる鬼を escape の (方向, 動かす行（列）, 操作回数) で落としてから、
+/// 必要なら初期盤面に戻すという操作列を返す
+pub fn fall_oni_toward(
+    y: usize,
+    x: usize,
+    escape: (Direction, usize, usize),
+    board_state: &BoardState,
+) -> Vec<MacroOp> {
+    assert_eq!(board_state.board[y][x], RoomState::Oni);
+
+    let (dir, index, min_op) = escape;
+
+    let mut res = vec![MacroOp {
+        dir,
+        index,
+        count: min_op,
+    }];
+
+    // 動かした行（列）に福がいないなら元に戻さなくてよい
+    match dir {
+        Direction::Left | Direction::Right => {
+            if !board_state.exists_fuku_in_row(index, 0, board_state.n) {
+                return res;
+            }
+        }
+        Direction::Down | Direction::Up => {
+            if !board_state.exists_fuku_in_col(x, 0, board_state.n) {
+                return res;
+            }
+        }
+    }
+
+    // 動かした後の盤面において、全ての鬼が四方を福に囲まれていなければ元に戻さなくてよい
+    let mut after_board = board_state.clone();
+    after_board.apply_macro(&res[0]);
+
+    if !after_board.exists_oni_around_all_fuku() {
+        return res;
+    }
+
+    // 初期盤面に戻す
+    res.push(MacroOp {
+        dir: get_rev_dir(dir),
+        index,
+        count: min_op,
+    });
+    res
+}
+
+/// 盤面から鬼がいなくなるまで、鬼を1体落として戻す操作を繰り返した操作列を返す
+pub fn solve(board_state: &BoardState) -> Vec<MacroOp> {
+    Solver::new(Config::default()).solve(board_state)
+}
+
+/// 操作列を適用すると福を1体も落とさずに鬼が全ていなくなり、操作回数も上限 4N^2 以内か
+pub fn is_valid_answer(board_state: &BoardState, ans: &[MacroOp]) -> bool {
+    let n = board_state.n;
+    let mut after_board = board_state.clone();
+    for macro_op in ans {
+        after_board.apply_macro(macro_op);
+    }
+    after_board.num_oni == 0
+        && after_board.num_fuku == board_state.num_fuku
+        && num_operations(ans) <= 4 * n * n
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    #[test]
+    fn test_fall_oni_and_reset() {
+        let board_state = generate_board_from_string(
+            5,
+            r"
+            o....
+            ...o.
+            .xxo.
+            oxo.o
+            .ox..
+        ",
+        )
+        .unwrap();
+
+        // 一番近いのは左に2回だが、(2, 3) の福が (2, 1) に来て (3, 1) の鬼が囲まれるので戻す必要があり4回かかる
+        // 上に3回なら (3, 1) の鬼も一緒に上端に動くだけで、囲まれる鬼がいないので戻さなくてよい
+        assert_eq!(nearest_escape(2, 1, &board_state).unwrap().2, 2);
+        assert_eq!(
+            expand(&fall_oni_and_reset(2, 1, &board_state))
+                .iter()
+                .map(|operation| operation.to_string())
+                .collect::<Vec<_>>(),
+            vec!["U 1", "U 1", "U 1"]
+        );
+    }
+
+    #[test]
+    fn test_removal_order_from_str() {
+        for order in RemovalOrder::ALL
+            .into_iter()
+            .chain([RemovalOrder::Lookahead(3)])
+        {
+            assert_eq!(order.to_string().parse::<RemovalOrder>(), Ok(order));
+        }
+        assert!("lookahead-0".parse::<RemovalOrder>().is_err());
+        assert!("lookahead-".parse::<RemovalOrder>().is_err());
+    }
+
+    #[test]
+    fn test_cheapest_removal() {
+        let board_state = generate_board_from_string(
+            4,
+            r"
+            .o..
+            .ox.
+            x...
+            ..o.
+        ",
+        )
+        .unwrap();
+
+        // (1, 2) は右に2回、(2, 0) は左に1回で落とせる
+        let ((y, x), macro_ops, after_board) = cheapest_removal(&board_state).unwrap();
+        assert_eq!((y, x), (2, 0));
+        assert_eq!(num_operations(&macro_ops), 1);
+        assert_eq!(after_board.num_oni, 1);
+        assert_eq!(lookahead_removal(&board_state, 2), Some((2, 0)));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::board::*;
+
+/// t回操作した後のboard_stateから、得点が上がる限り福を落としてでも鬼を落とす操作を足す
+///
+/// 全ての行（列）を1..=N回動かす操作のうち、得点の増え方が一番大きい（同じなら操作回数が少ない）ものを
+/// 選ぶことを繰り返す。鬼が残っていれば得点は操作回数によらないので、福を1つ落として鬼を2体落とす操作も選ばれる。
+/// 操作回数は上限 4N^2 を超えない。
+pub fn finishing_moves(board_state: &BoardState, num_fuku: usize, t: usize) -> Vec<MacroOp> {
+    let n = board_state.n;
+    let mut board_state = board_state.clone();
+    let mut t = t;
+    let mut res = Vec::new();
+    while board_state.num_oni > 0 {
+        let current = compute_score(n, t, board_state.num_oni, num_fuku - board_state.num_fuku);
+        let mut best: Option<(i64, MacroOp, BoardState)> = None;
+        for dir in Direction::ALL {
+            for index in 0..n {
+                for count in 1..=n.min(4 * n * n - t) {
+                    let macro_op = MacroOp { dir, index, count };
+                    let mut after_board = board_state.clone();
+                    after_board.apply_macro(&macro_op);
+                    let gain = compute_score(
+                        n,
+                        t + count,
+                        after_board.num_oni,
+                        num_fuku - after_board.num_fuku,
+                    ) - current;
+                    if best.as_ref().map_or(true, |(best_gain, best_op, _)| {
+                        (gain, std::cmp::Reverse(count))
+                            > (*best_gain, std::cmp::Reverse(best_op.count))
+                    }) {
+                        best = Some((gain, macro_op, after_board));
+                    }
+                }
+            }
+        }
+        match best {
+            Some((gain, macro_op, after_board)) if gain > 0 => {
+                board_state = after_board;
+                t += macro_op.count;
+                res.push(macro_op);
+            }
+            _ => break,
+        }
+    }
+    res
+}
+
+/// 操作列を先頭から動かし、得点が一番高くなるところで打ち切って `finishing_moves` を足したものを返す
+///
+/// 全ての鬼を落とせなかった操作列や、上限 4N^2 を超えた操作列も、コンテストの得点
+/// （全て落とせば 8N^2 - T、そうでなければ 4N^2 - N(X + Y)）が一番高い形にする。
+/// 打ち切る位置は得点が一番高い位置と、上限を超えない一番長い位置の2つを試す。
+pub fn truncate(board_state: &BoardState, ans: &[Operation]) -> Vec<Operation> {
+    let n = board_state.n;
+    let num_fuku = board_state.num_fuku;
+    let len = ans.len().min(4 * n * n);
+
+    let mut after_board = board_state.clone();
+    let mut best = (compute_score_of(board_state, &[]), 0, after_board.clone());
+    for (k, operation) in ans[..len].iter().enumerate() {
+        after_board.apply(operation);
+        let s = compute_score(
+            n,
+            k + 1,
+            after_board.num_oni,
+            num_fuku - after_board.num_fuku,
+        );
+        if s > best.0 {
+            best = (s, k + 1, after_board.clone());
+        }
+    }
+
+    let mut res: Option<(i64, Vec<Operation>)> = None;
+    for (k, prefix_board) in [(best.1, best.2), (len, after_board)] {
+        let finish = finishing_moves(&prefix_board, num_fuku, k);
+        let mut finished_board = prefix_board;
+        for macro_op in &finish {
+            finished_board.apply_macro(macro_op);
+        }
+        let s = compute_score(
+            n,
+            k + num_operations(&finish),
+            finished_board.num_oni,
+            num_fuku - finished_board.num_fuku,
+        );
+        if res.as_ref().map_or(true, |(best_score, _)| s > *best_score) {
+            let mut truncated = ans[..k].to_vec();
+            truncated.extend(expand(&finish));
+            res = Some((s, truncated));
+        }
+    }
+    res.unwrap().1
+}
+
+#[cfg(test)]
+mod unittests {
+    use super::*;
+
+    fn to_strings(ans: &[Operation]) -> Vec<String> {
+        ans.iter().map(|operation| operation.to_string()).collect()
+    }
+
+    #[test]
+    fn test_truncate_wasted_tail() {
+        let board_state = generate_board_from_string(
+            3,
+            r"
+            ...
+            x.o
+            .xo
+        ",
+        )
+        .unwrap();
+        // 2回で全て落とした後に福を落とす操作が続いている
+        let ans = [
+            Operation {
+                dir: Direction::Left,
+                index: 1,
+            },
+            Operation {
+                dir: Direction::Down,
+                index: 1,
+            },
+            Operation {
+                dir: Direction::Right,
+                index: 2,
+            },
+        ];
+
+        assert_eq!(
+            to_strings(&truncate(&board_state, &ans)),
+            vec!["L 1", "D 1"]
+        );
+    }
+
+    #[test]
+    fn test_truncate_finishing_moves() {
+        let board_state = generate_board_from_string(
+            4,
+            r"
+            .oo.
+            oxxo
+            .oo.
+            ....
+        ",
+        )
+        .unwrap();
+
+        // 2体の鬼はどちらも福に囲まれていて、何もしなければ 4 * 16 - 4 * 2 = 56 点
+        // 1行目を左に3回動かすと福を1つ落として2体とも落とせて 4 * 16 - 4 * 1 = 60 点
+        let ans = truncate(&board_state, &[]);
+
+        assert_eq!(to_strings(&ans), vec!["L 1", "L 1", "L 1"]);
+    }
+}
@@ -0,0 +1,28 @@
+//! 打ち切りのフラグはプロセス全体で共有されるので、他のテストと別のバイナリで確かめる
+use ahc042::board::*;
+use ahc042::interrupt;
+use ahc042::portfolio::{greedy_fallback, solve_portfolio};
+use ahc042::solver::is_valid_answer;
+
+#[test]
+fn test_greedy_fallback_after_interrupt() {
+    let board_state = generate_board_from_string(
+        6,
+        r"
+        .x..x.
+        o..x..
+        .x.o.x
+        x...o.
+        ..x..o
+        .o..x.
+    ",
+    )
+    .unwrap();
+
+    interrupt::interrupt();
+    // 打ち切られた後は新しい設定を試さない
+    assert!(solve_portfolio(&board_state, None, 0).is_none());
+
+    let (_, ans) = greedy_fallback(&board_state, 0);
+    assert!(is_valid_answer(&board_state, &ans));
+}
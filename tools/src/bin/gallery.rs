@@ -0,0 +1,197 @@
+#![allow(non_snake_case)]
+
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use tools::*;
+
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Path to input directory
+    #[clap(default_value = "in")]
+    input: PathBuf,
+    /// Path to output directory; each output file has the same name as its input file
+    #[clap(default_value = "out")]
+    output: PathBuf,
+    /// Directory to write index.html, one page per case and the thumbnails to
+    #[clap(short = 'd', long = "dir", default_value = "gallery")]
+    dir: PathBuf,
+}
+
+/// Result of one input/output pair.
+struct Case {
+    name: String,
+    score: i64,
+    T: usize,
+    X: usize,
+    Y: usize,
+    err: String,
+}
+
+fn read_file(file: &Path) -> String {
+    std::fs::read_to_string(file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", file.display());
+        std::process::exit(1)
+    })
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Scores one case and writes its thumbnail and detail page to `dir`.
+fn render_case(dir: &Path, name: &str, input: &Input, output: &str) -> Case {
+    let (out, err) = match parse_output(input, output) {
+        Ok(out) => (out.out, String::new()),
+        Err(err) => (vec![], err),
+    };
+    let (score, vis_err, svg) = vis_default(input, &Output { out: out.clone() });
+    let err = if err.is_empty() { vis_err } else { err };
+    let (timeline, _) = compute_score_timeline(input, &out);
+    let last = *timeline.last().unwrap();
+    let case = Case {
+        name: name.to_owned(),
+        score: if err.is_empty() { score } else { 0 },
+        T: last.t,
+        X: last.oni,
+        Y: last.lost_fuku,
+        err,
+    };
+    std::fs::write(dir.join("svg").join(format!("{}.svg", name)), &svg).unwrap();
+    let page = format!(
+        r#"<html>
+<head>
+<meta charset="utf-8">
+<title>{name}</title>
+<style>body {{font-family: sans-serif;}}</style>
+</head>
+<body>
+<p><a href="index.html">index</a></p>
+<h1>{name}</h1>
+<p>Score = {score}, T = {T}, X = {X}, Y = {Y}</p>
+<p style="color: red">{err}</p>
+{svg}{steps}<br>{timeline}
+</body>
+</html>
+"#,
+        name = name,
+        score = case.score,
+        T = case.T,
+        X = case.X,
+        Y = case.Y,
+        err = escape(&case.err),
+        svg = svg,
+        steps = vis_steps(input, &out, &timeline),
+        timeline = vis_timeline(&timeline),
+    );
+    std::fs::write(dir.join(format!("{}.html", name)), page).unwrap();
+    case
+}
+
+fn index_html(cases: &[Case]) -> String {
+    let total = cases.iter().map(|c| c.score).sum::<i64>();
+    let rows = cases
+        .iter()
+        .map(|c| {
+            format!(
+                r#"<tr data-name="{name}" data-score="{score}" data-t="{T}" data-x="{X}">
+<td><a href="{name}.html"><img src="svg/{name}.svg" width="160"></a></td>
+<td><a href="{name}.html">{name}</a></td><td>{score}</td><td>{T}</td><td>{X}</td><td>{Y}</td>
+<td style="color: red">{err}</td>
+</tr>
+"#,
+                name = c.name,
+                score = c.score,
+                T = c.T,
+                X = c.X,
+                Y = c.Y,
+                err = escape(&c.err),
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<html>
+<head>
+<meta charset="utf-8">
+<title>AHC042 gallery</title>
+<style>
+body {{font-family: sans-serif;}}
+th {{cursor: pointer; user-select: none;}}
+td {{padding: 0 8px; text-align: right;}}
+</style>
+</head>
+<body>
+<p>{num} cases, total score = {total}. Click a column header to sort.</p>
+<table>
+<thead><tr>
+<th></th>
+<th onclick="sort_by('name', false)">Case</th>
+<th onclick="sort_by('score', true)">Score</th>
+<th onclick="sort_by('t', true)">T</th>
+<th onclick="sort_by('x', true)">X</th>
+<th>Y</th>
+<th></th>
+</tr></thead>
+<tbody id="cases">
+{rows}</tbody>
+</table>
+<script>
+let last_key = null;
+let ascending = true;
+function sort_by(key, numeric) {{
+    ascending = key == last_key ? !ascending : true;
+    last_key = key;
+    const tbody = document.getElementById("cases");
+    const rows = Array.from(tbody.rows);
+    rows.sort((a, b) => {{
+        const x = a.dataset[key];
+        const y = b.dataset[key];
+        const c = numeric ? Number(x) - Number(y) : x.localeCompare(y);
+        return ascending ? c : -c;
+    }});
+    tbody.append(...rows);
+}}
+</script>
+</body>
+</html>
+"#,
+        num = cases.len(),
+        total = total,
+        rows = rows,
+    )
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let mut inputs = std::fs::read_dir(&cli.input)
+        .unwrap_or_else(|_| {
+            eprintln!("no such directory: {}", cli.input.display());
+            std::process::exit(1)
+        })
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    inputs.sort();
+    std::fs::create_dir_all(cli.dir.join("svg")).unwrap();
+
+    let mut cases = vec![];
+    for in_file in inputs {
+        let out_file = cli.output.join(in_file.file_name().unwrap());
+        if !out_file.is_file() {
+            eprintln!("skipped (no output): {}", in_file.display());
+            continue;
+        }
+        let input = match parse_input(&read_file(&in_file)) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("skipped (invalid input): {}: {}", in_file.display(), err);
+                continue;
+            }
+        };
+        let name = in_file.file_stem().unwrap().to_string_lossy().into_owned();
+        cases.push(render_case(&cli.dir, &name, &input, &read_file(&out_file)));
+    }
+
+    std::fs::write(cli.dir.join("index.html"), index_html(&cases)).unwrap();
+    println!("{} cases, total score = {}", cases.len(), cases.iter().map(|c| c.score).sum::<i64>());
+    println!("wrote {}", cli.dir.join("index.html").display());
+}
@@ -12,8 +12,56 @@ struct Cli {
     #[clap(short = 'd', long = "dir", default_value = "in")]
     dir: PathBuf,
     #[clap(short, long)]
-    /// Print input details in csv format
+    /// Print input details and features in csv format
     verbose: bool,
+    /// Board size
+    #[clap(short = 'n', long = "size", default_value_t = 20)]
+    size: usize,
+    /// Number of fuku [default: 2 * size]
+    #[clap(long = "fuku")]
+    fuku: Option<usize>,
+    /// Number of oni [default: 2 * size]
+    #[clap(long = "oni")]
+    oni: Option<usize>,
+    /// Spatial distribution of fuku: uniform, center or border
+    #[clap(long = "density", default_value = "uniform")]
+    density: Density,
+    /// Place oni anywhere, without guaranteeing that every oni can escape
+    #[clap(long = "no-guarantee")]
+    no_guarantee: bool,
+    /// Search for inputs that are hard for this solver binary by hill-climbing
+    #[clap(long = "hard")]
+    hard: Option<PathBuf>,
+    /// Number of hill-climbing steps per seed with --hard
+    #[clap(long = "iterations", default_value_t = 200)]
+    iterations: usize,
+    /// What --hard maximizes: ops (operations used) or gap (operations above the lower bound)
+    #[clap(long = "objective", default_value = "ops")]
+    objective: Objective,
+}
+
+/// Runs the solver on `input` and returns its score, or `None` if it fails or prints an invalid output.
+fn run_solver(solver: &std::path::Path, input: &Input) -> Option<i64> {
+    let mut child = std::process::Command::new(solver)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap_or_else(|_| {
+            eprintln!("failed to run: {}", solver.display());
+            std::process::exit(1)
+        });
+    write!(child.stdin.take().unwrap(), "{}", input).ok()?;
+    let result = child.wait_with_output().ok()?;
+    if !result.status.success() {
+        return None;
+    }
+    let out = parse_output(input, &String::from_utf8_lossy(&result.stdout)).ok()?;
+    let (score, err) = compute_score(input, &out);
+    if !err.is_empty() {
+        return None;
+    }
+    Some(score)
 }
 
 fn main() {
@@ -25,24 +73,48 @@ fn main() {
         eprintln!("no such file: {}", cli.seeds);
         std::process::exit(1)
     });
+    let mut params = GenParams::new(cli.size);
+    if let Some(fuku) = cli.fuku {
+        params.num_fuku = fuku;
+    }
+    if let Some(oni) = cli.oni {
+        params.num_oni = oni;
+    }
+    params.density = cli.density;
+    params.guarantee = !cli.no_guarantee;
     let f = std::io::BufReader::new(f);
     let mut id = 0;
     if cli.verbose {
-        println!("file,seed");
+        if cli.hard.is_some() {
+            println!("file,seed,hardness,{}", Features::CSV_HEADER);
+        } else {
+            println!("file,seed,{}", Features::CSV_HEADER);
+        }
     }
     for line in f.lines() {
         let line = line.unwrap();
         let line = line.trim();
-        if line.len() == 0 {
+        if line.is_empty() {
             continue;
         }
         let seed = line.parse::<u64>().unwrap_or_else(|_| {
             eprintln!("parse failed: {}", line);
             std::process::exit(1)
         });
-        let input = gen(seed);
+        let (input, hardness) = match &cli.hard {
+            Some(solver) => gen_hard(seed, &params, cli.iterations, cli.objective, |input| run_solver(solver, input))
+                .map(|(input, hardness)| (input, Some(hardness))),
+            None => gen_with(seed, &params).map(|input| (input, None)),
+        }
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        });
         if cli.verbose {
-            println!("{:04},{}", id, seed);
+            match hardness {
+                Some(hardness) => println!("{:04},{},{},{}", id, seed, hardness, features(&input)),
+                None => println!("{:04},{},{}", id, seed, features(&input)),
+            }
         }
         let mut w = std::io::BufWriter::new(std::fs::File::create(cli.dir.join(format!("{:04}.txt", id))).unwrap());
         write!(w, "{}", input).unwrap();
@@ -1,34 +1,85 @@
 #![allow(non_snake_case)]
 
+use clap::Parser;
 use tools::*;
 
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Path to input file
+    input: String,
+    /// Path to output file (optional with --manual)
+    output: Option<String>,
+    #[clap(short, long)]
+    /// Write a manual play page to vis.html, starting from the moves in the output file if given
+    manual: bool,
+    #[clap(long = "all-errors")]
+    /// Report every invalid operation in the output file instead of stopping at the first one
+    all_errors: bool,
+    #[clap(long = "timeline")]
+    /// Write the remaining oni, lost fuku and score after each operation to this CSV file
+    timeline: Option<String>,
+}
+
+fn read_file(file: &str) -> String {
+    std::fs::read_to_string(file).unwrap_or_else(|_| {
+        eprintln!("no such file: {}", file);
+        std::process::exit(1)
+    })
+}
+
 fn main() {
-    if std::env::args().len() != 3 {
-        eprintln!("Usage: {} <input> <output>", std::env::args().nth(0).unwrap());
-        return;
-    }
-    let in_file = std::env::args().nth(1).unwrap();
-    let out_file = std::env::args().nth(2).unwrap();
-    let input = std::fs::read_to_string(&in_file).unwrap_or_else(|_| {
-        eprintln!("no such file: {}", in_file);
+    let cli = Cli::parse();
+    let input = parse_input(&read_file(&cli.input)).unwrap_or_else(|err| {
+        eprintln!("invalid input: {}", err);
         std::process::exit(1)
     });
-    let output = std::fs::read_to_string(&out_file).unwrap_or_else(|_| {
-        eprintln!("no such file: {}", out_file);
+    if cli.manual {
+        let out = match &cli.output {
+            Some(out_file) => parse_output(&input, &read_file(out_file)).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }),
+            None => Output { out: vec![] },
+        };
+        std::fs::write("vis.html", vis_manual(&input, &out.out)).unwrap();
+        return;
+    }
+    let Some(out_file) = cli.output else {
+        eprintln!("Usage: vis <input> <output>");
         std::process::exit(1)
-    });
-    let input = parse_input(&input);
-    let out = parse_output(&input, &output);
-    let (score, err, svg) = match out {
-        Ok(out) => vis_default(&input, &out),
-        Err(err) => (0, err, String::new()),
     };
-    if err.len() > 0 {
+    let output = read_file(&out_file);
+    let out = if cli.all_errors {
+        parse_output_all(&input, &output).map_err(|errs| errs.join("\n"))
+    } else {
+        parse_output(&input, &output)
+    };
+    let (score, err, svg, out, timeline) = match out {
+        Ok(out) => {
+            let (score, err, svg) = vis_default(&input, &out);
+            let timeline = compute_score_timeline(&input, &out.out).0;
+            (score, err, svg, out.out, timeline)
+        }
+        Err(err) => (0, err, String::new(), vec![], vec![]),
+    };
+    if let Some(file) = &cli.timeline {
+        let mut csv = "t,oni,lost_fuku,score\n".to_owned();
+        for s in &timeline {
+            csv += &format!("{},{},{},{}\n", s.t, s.oni, s.lost_fuku, s.score);
+        }
+        std::fs::write(file, csv).unwrap();
+    }
+    if !err.is_empty() {
         println!("{}", err);
         println!("Score = {}", 0);
     } else {
         println!("Score = {}", score);
     }
-    let vis = format!("<html><body>{}</body></html>", svg);
-    std::fs::write("vis.html", &vis).unwrap();
+    let vis = format!(
+        "<html><body>{}{}<br>{}</body></html>",
+        svg,
+        vis_steps(&input, &out, &timeline),
+        vis_timeline(&timeline)
+    );
+    std::fs::write("vis.html", vis).unwrap();
 }
@@ -1,9 +1,8 @@
 #![allow(non_snake_case, unused_macros)]
 
-use proconio::{input, marker::Chars};
 use rand::prelude::*;
 use std::ops::RangeBounds;
-use svg::node::element::{Definitions, Group, Image, Rectangle, Style, Text, Title, Use};
+use svg::node::element::{Circle, Definitions, Group, Image, Polyline, Rectangle, Style, Text, Title, Use};
 
 pub trait SetMinMax {
     fn setmin(&mut self, v: Self) -> bool;
@@ -50,15 +49,25 @@ impl std::fmt::Display for Input {
     }
 }
 
-pub fn parse_input(f: &str) -> Input {
-    let f = proconio::source::once::OnceSource::from(f);
-    input! {
-        from f,
-        n: usize,
-        cs: [Chars; n],
+pub fn parse_input(f: &str) -> Result<Input, String> {
+    let mut f = f.split_whitespace();
+    let n = read(f.next(), 1..)?;
+    let mut cs = vec![];
+    for i in 0..n {
+        let row = f
+            .next()
+            .ok_or_else(|| "Unexpected EOF".to_owned())?
+            .chars()
+            .collect::<Vec<_>>();
+        if row.len() != n {
+            return Err(format!("Row {}: expected {} cells, found {}", i, n, row.len()));
+        }
+        if let Some(c) = row.iter().find(|&&c| !matches!(c, 'x' | 'o' | '.')) {
+            return Err(format!("Row {}: invalid cell: {}", i, c));
+        }
+        cs.push(row);
     }
-    let cs = cs.iter().map(|cs| cs.to_vec()).collect();
-    Input { cs }
+    Ok(Input { cs })
 }
 
 pub fn read<T: Copy + PartialOrd + std::fmt::Display + std::str::FromStr, R: RangeBounds<T>>(
@@ -85,28 +94,153 @@ pub struct Output {
 }
 
 pub fn parse_output(input: &Input, f: &str) -> Result<Output, String> {
+    parse_output_impl(input, f, true).map_err(|mut errs| errs.swap_remove(0))
+}
+
+/// Like `parse_output`, but keeps going after an invalid operation and returns every error found.
+pub fn parse_output_all(input: &Input, f: &str) -> Result<Output, Vec<String>> {
+    parse_output_impl(input, f, false)
+}
+
+fn parse_output_impl(input: &Input, f: &str, stop_at_first: bool) -> Result<Output, Vec<String>> {
+    let n = input.cs.len();
     let mut out = vec![];
-    let mut f = f.split_whitespace().peekable();
-    while f.peek().is_some() {
-        let dir = read(f.next(), 'A'..='Z')?;
-        let p = read(f.next(), 0..input.cs.len())?;
-        out.push((dir, p));
-        if out.len() > 4 * input.cs.len() * input.cs.len() {
-            return Err("Too many operations".to_owned());
+    let mut errs = vec![];
+    let mut f = f
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| line.split_whitespace().map(move |token| (i + 1, token)));
+    let mut k = 0;
+    while let Some((op_line, token)) = f.next() {
+        k += 1;
+        // Errors are reported at the line of the direction token, even if the index is on a later line.
+        let at = |err: String| format!("Operation {} (line {}): {}", k, op_line, err);
+        let dir = read(Some(token), 'A'..='Z').and_then(|d| {
+            if matches!(d, 'L' | 'R' | 'U' | 'D') {
+                Ok(d)
+            } else {
+                Err(format!("Invalid direction: {}", d))
+            }
+        });
+        let p = read(f.next().map(|(_, token)| token), 0..n);
+        match (dir, p) {
+            (Ok(dir), Ok(p)) => out.push((dir, p)),
+            (dir, p) => {
+                errs.extend(dir.err().into_iter().chain(p.err()).map(at));
+                if stop_at_first {
+                    return Err(errs);
+                }
+            }
+        }
+        if k > 4 * n * n {
+            errs.push(at("Too many operations".to_owned()));
+            break;
+        }
+    }
+    if errs.is_empty() {
+        Ok(Output { out })
+    } else {
+        Err(errs)
+    }
+}
+
+/// Spatial distribution used when placing fuku.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Density {
+    Uniform,
+    /// Fuku cluster around the center of the board.
+    Center,
+    /// Fuku cluster near the edges of the board.
+    Border,
+}
+
+impl std::str::FromStr for Density {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Density::Uniform),
+            "center" => Ok(Density::Center),
+            "border" => Ok(Density::Border),
+            _ => Err(format!("unknown density: {} (expected uniform, center or border)", s)),
         }
     }
-    Ok(Output { out })
+}
+
+#[derive(Clone, Debug)]
+pub struct GenParams {
+    pub n: usize,
+    pub num_fuku: usize,
+    pub num_oni: usize,
+    pub density: Density,
+    /// Place oni only where they can leave the board without pushing out any fuku.
+    pub guarantee: bool,
+}
+
+impl GenParams {
+    /// Parameters of the contest scaled to an n x n board.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            num_fuku: n * 2,
+            num_oni: n * 2,
+            density: Density::Uniform,
+            guarantee: true,
+        }
+    }
+}
+
+impl Default for GenParams {
+    fn default() -> Self {
+        Self::new(20)
+    }
 }
 
 pub fn gen(seed: u64) -> Input {
+    gen_with(seed, &GenParams::default()).unwrap()
+}
+
+/// Relative weight of cell (i, j) for the given density.
+fn density_weight(n: usize, density: Density, i: usize, j: usize) -> f64 {
+    let c = (n - 1) as f64 / 2.0;
+    let sigma = n as f64 / 4.0;
+    let d2 = (i as f64 - c).powi(2) + (j as f64 - c).powi(2);
+    let g = (-d2 / (2.0 * sigma * sigma)).exp();
+    match density {
+        Density::Uniform => 1.0,
+        Density::Center => g,
+        Density::Border => 1.0 - g + 1e-3,
+    }
+}
+
+#[allow(clippy::collapsible_if)]
+pub fn gen_with(seed: u64, params: &GenParams) -> Result<Input, String> {
+    let n = params.n;
+    if n == 0 {
+        return Err("Board size must be positive".to_owned());
+    }
+    if params.num_fuku + params.num_oni > n * n {
+        return Err(format!(
+            "Too many pieces: {} fuku and {} oni on a {}x{} board",
+            params.num_fuku, params.num_oni, n, n
+        ));
+    }
     let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
-    loop {
-        let n = 20;
+    let weights = rand::distributions::WeightedIndex::new(
+        (0..n * n).map(|k| density_weight(n, params.density, k / n, k % n)),
+    )
+    .unwrap();
+    for _ in 0..1000 {
         let mut cs = mat!['.'; n; n];
-        for _ in 0..n * 2 {
+        for _ in 0..params.num_fuku {
             let (i, j) = loop {
-                let i = rng.gen_range(0..n as i32) as usize;
-                let j = rng.gen_range(0..n as i32) as usize;
+                let (i, j) = if params.density == Density::Uniform {
+                    let i = rng.gen_range(0..n as i32) as usize;
+                    let j = rng.gen_range(0..n as i32) as usize;
+                    (i, j)
+                } else {
+                    let k = weights.sample(&mut rng);
+                    (k / n, k % n)
+                };
                 if cs[i][j] == '.' {
                     break (i, j);
                 }
@@ -117,7 +251,8 @@ pub fn gen(seed: u64) -> Input {
         for i in 0..n {
             for j in 0..n {
                 if cs[i][j] == '.' {
-                    if (0..i).all(|i| cs[i][j] != 'o')
+                    if !params.guarantee
+                        || (0..i).all(|i| cs[i][j] != 'o')
                         || (i + 1..n).all(|i| cs[i][j] != 'o')
                         || (0..j).all(|j| cs[i][j] != 'o')
                         || (j + 1..n).all(|j| cs[i][j] != 'o')
@@ -127,17 +262,212 @@ pub fn gen(seed: u64) -> Input {
                 }
             }
         }
-        if cand.len() < n * 2 {
+        if cand.len() < params.num_oni {
             continue;
         }
         cand.shuffle(&mut rng);
-        for &(i, j) in cand.iter().take(n * 2) {
+        for &(i, j) in cand.iter().take(params.num_oni) {
             cs[i][j] = 'x';
         }
-        return Input { cs };
+        return Ok(Input { cs });
+    }
+    Err(format!(
+        "Failed to place {} oni with the escape guarantee; try fewer fuku or oni",
+        params.num_oni
+    ))
+}
+
+/// Whether every oni can leave the board along its row or column without pushing out a fuku.
+pub fn is_guaranteed(input: &Input) -> bool {
+    let cs = &input.cs;
+    let n = cs.len();
+    (0..n).all(|i| {
+        (0..n).all(|j| {
+            cs[i][j] != 'x'
+                || (0..i).all(|i| cs[i][j] != 'o')
+                || (i + 1..n).all(|i| cs[i][j] != 'o')
+                || (0..j).all(|j| cs[i][j] != 'o')
+                || (j + 1..n).all(|j| cs[i][j] != 'o')
+        })
+    })
+}
+
+/// A lower bound on the number of operations needed to remove every oni.
+/// One operation drops at most one oni, and each oni needs at least as many
+/// operations as its distance to the nearest edge.
+pub fn lower_bound(input: &Input) -> usize {
+    let n = input.cs.len();
+    let mut num_oni = 0;
+    let mut max_dist = 0;
+    for i in 0..n {
+        for j in 0..n {
+            if input.cs[i][j] == 'x' {
+                num_oni += 1;
+                max_dist.setmax((i + 1).min(n - i).min(j + 1).min(n - j));
+            }
+        }
+    }
+    num_oni.max(max_dist)
+}
+
+/// Structural features of an input, printed by `gen --verbose`.
+#[derive(Clone, Debug, Default)]
+pub struct Features {
+    /// Number of oni that can leave upwards (downwards, ...) without pushing out a fuku.
+    /// An oni escapable in several directions is counted in each of them.
+    pub escape_up: usize,
+    pub escape_down: usize,
+    pub escape_left: usize,
+    pub escape_right: usize,
+    /// Number of rows / columns without fuku.
+    pub free_rows: usize,
+    pub free_cols: usize,
+    /// Average over escapable oni of the shortest escape, in operations.
+    pub avg_escape_dist: f64,
+    /// Number of oni sharing a row or column with another oni.
+    pub shared_oni: usize,
+}
+
+impl Features {
+    pub const CSV_HEADER: &'static str =
+        "escape_up,escape_down,escape_left,escape_right,free_rows,free_cols,avg_escape_dist,shared_oni";
+}
+
+impl std::fmt::Display for Features {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{},{:.3},{}",
+            self.escape_up,
+            self.escape_down,
+            self.escape_left,
+            self.escape_right,
+            self.free_rows,
+            self.free_cols,
+            self.avg_escape_dist,
+            self.shared_oni
+        )
     }
 }
 
+pub fn features(input: &Input) -> Features {
+    let cs = &input.cs;
+    let n = cs.len();
+    let mut features = Features {
+        free_rows: (0..n).filter(|&i| (0..n).all(|j| cs[i][j] != 'o')).count(),
+        free_cols: (0..n).filter(|&j| (0..n).all(|i| cs[i][j] != 'o')).count(),
+        ..Default::default()
+    };
+    let oni_in_row = (0..n).map(|i| (0..n).filter(|&j| cs[i][j] == 'x').count()).collect::<Vec<_>>();
+    let oni_in_col = (0..n).map(|j| (0..n).filter(|&i| cs[i][j] == 'x').count()).collect::<Vec<_>>();
+    let mut num_escapable = 0;
+    let mut sum_dist = 0;
+    for i in 0..n {
+        for (j, &c) in cs[i].iter().enumerate() {
+            if c != 'x' {
+                continue;
+            }
+            if oni_in_row[i] > 1 || oni_in_col[j] > 1 {
+                features.shared_oni += 1;
+            }
+            let mut dist = !0;
+            if (0..i).all(|i| cs[i][j] != 'o') {
+                features.escape_up += 1;
+                dist.setmin(i + 1);
+            }
+            if (i + 1..n).all(|i| cs[i][j] != 'o') {
+                features.escape_down += 1;
+                dist.setmin(n - i);
+            }
+            if (0..j).all(|j| cs[i][j] != 'o') {
+                features.escape_left += 1;
+                dist.setmin(j + 1);
+            }
+            if (j + 1..n).all(|j| cs[i][j] != 'o') {
+                features.escape_right += 1;
+                dist.setmin(n - j);
+            }
+            if dist != !0 {
+                num_escapable += 1;
+                sum_dist += dist;
+            }
+        }
+    }
+    if num_escapable > 0 {
+        features.avg_escape_dist = sum_dist as f64 / num_escapable as f64;
+    }
+    features
+}
+
+/// What the hard-instance search maximizes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    /// The number of operations the solver needs (`8N^2 - score`).
+    Ops,
+    /// The number of operations above `lower_bound`.
+    Gap,
+}
+
+impl std::str::FromStr for Objective {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ops" => Ok(Objective::Ops),
+            "gap" => Ok(Objective::Gap),
+            _ => Err(format!("unknown objective: {} (expected ops or gap)", s)),
+        }
+    }
+}
+
+/// Hill-climbs from `gen_with(seed, params)` towards inputs on which the solver does badly.
+///
+/// `eval` returns the solver's score on an input, or `None` if the solver could not be run.
+/// Each step moves one piece to a random empty cell and keeps the result if it is at least as
+/// bad as the current input. Returns the hardest input found and its badness.
+pub fn gen_hard(
+    seed: u64,
+    params: &GenParams,
+    iterations: usize,
+    objective: Objective,
+    mut eval: impl FnMut(&Input) -> Option<i64>,
+) -> Result<(Input, i64), String> {
+    let mut input = gen_with(seed, params)?;
+    let n = params.n;
+    let badness = |input: &Input, score: i64| {
+        let bad = (8 * n * n) as i64 - score;
+        match objective {
+            Objective::Ops => bad,
+            Objective::Gap => bad - lower_bound(input) as i64,
+        }
+    };
+    let score = eval(&input).ok_or("Failed to evaluate the initial input")?;
+    let mut cur = badness(&input, score);
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
+    for _ in 0..iterations {
+        let cells = (0..n).flat_map(|i| (0..n).map(move |j| (i, j)));
+        let (pieces, vacant): (Vec<_>, Vec<_>) = cells.partition(|&(i, j)| input.cs[i][j] != '.');
+        let (Some(&(i1, j1)), Some(&(i2, j2))) = (pieces.choose(&mut rng), vacant.choose(&mut rng)) else {
+            break;
+        };
+        let mut next = input.clone();
+        next.cs[i2][j2] = next.cs[i1][j1];
+        next.cs[i1][j1] = '.';
+        if params.guarantee && !is_guaranteed(&next) {
+            continue;
+        }
+        let Some(score) = eval(&next) else {
+            continue;
+        };
+        let bad = badness(&next, score);
+        if bad >= cur {
+            cur = bad;
+            input = next;
+        }
+    }
+    Ok((input, cur))
+}
+
+#[allow(clippy::len_zero)]
 pub fn compute_score(input: &Input, out: &Output) -> (i64, String) {
     let (mut score, err, _) = compute_score_details(input, &out.out);
     if err.len() > 0 {
@@ -146,6 +476,64 @@ pub fn compute_score(input: &Input, out: &Output) -> (i64, String) {
     (score, err)
 }
 
+/// Shifts row or column `p` of `cs` in direction `d` and returns the piece pushed off the board ('.' if none).
+pub fn apply_operation(cs: &mut [Vec<char>], d: char, p: usize) -> Result<char, String> {
+    let n = cs.len();
+    if p >= n {
+        return Err(format!("Out of range: {}", p));
+    }
+    let fallen;
+    match d {
+        'L' => {
+            let i = p;
+            fallen = cs[i][0];
+            for j in 0..n - 1 {
+                cs[i][j] = cs[i][j + 1];
+            }
+            cs[i][n - 1] = '.';
+        }
+        'R' => {
+            let i = p;
+            fallen = cs[i][n - 1];
+            for j in (1..n).rev() {
+                cs[i][j] = cs[i][j - 1];
+            }
+            cs[i][0] = '.';
+        }
+        'U' => {
+            let j = p;
+            fallen = cs[0][j];
+            for i in 0..n - 1 {
+                cs[i][j] = cs[i + 1][j];
+            }
+            cs[n - 1][j] = '.';
+        }
+        'D' => {
+            let j = p;
+            fallen = cs[n - 1][j];
+            for i in (1..n).rev() {
+                cs[i][j] = cs[i - 1][j];
+            }
+            cs[0][j] = '.';
+        }
+        _ => {
+            return Err(format!("Invalid direction: {}", d));
+        }
+    }
+    Ok(fallen)
+}
+
+/// The contest score after T operations with X oni left and Y fuku lost.
+pub fn score_of(n: usize, T: usize, X: usize, Y: usize) -> i64 {
+    let (n, T, X, Y) = (n as i64, T as i64, X as i64, Y as i64);
+    if X == 0 && Y == 0 {
+        8 * n * n - T
+    } else {
+        4 * n * n - n * (X + Y)
+    }
+}
+
+#[allow(clippy::needless_range_loop)]
 pub fn compute_score_details(
     input: &Input,
     out: &[(char, usize)],
@@ -153,43 +541,13 @@ pub fn compute_score_details(
     let n = input.cs.len();
     let mut cs = input.cs.clone();
     for &(d, p) in out {
-        match d {
-            'L' => {
-                let i = p;
-                for j in 0..n - 1 {
-                    cs[i][j] = cs[i][j + 1];
-                }
-                cs[i][n - 1] = '.';
-            }
-            'R' => {
-                let i = p;
-                for j in (1..n).rev() {
-                    cs[i][j] = cs[i][j - 1];
-                }
-                cs[i][0] = '.';
-            }
-            'U' => {
-                let j = p;
-                for i in 0..n - 1 {
-                    cs[i][j] = cs[i + 1][j];
-                }
-                cs[n - 1][j] = '.';
-            }
-            'D' => {
-                let j = p;
-                for i in (1..n).rev() {
-                    cs[i][j] = cs[i - 1][j];
-                }
-                cs[0][j] = '.';
-            }
-            _ => {
-                return (0, format!("Invalid direction: {}", d), cs);
-            }
+        if let Err(err) = apply_operation(&mut cs, d, p) {
+            return (0, err, cs);
         }
     }
     let T = out.len();
     let mut X = 0;
-    let mut Y = 2 * n;
+    let mut Y = input.cs.iter().flatten().filter(|&&c| c == 'o').count();
     for i in 0..n {
         for j in 0..n {
             if cs[i][j] == 'x' {
@@ -200,12 +558,108 @@ pub fn compute_score_details(
             }
         }
     }
-    let score = if X == 0 && Y == 0 {
-        8 * n * n - T
-    } else {
-        4 * n * n - n * (X + Y)
-    };
-    (score as i64, String::new(), cs)
+    (score_of(n, T, X, Y), String::new(), cs)
+}
+
+/// State of the board after the first `t` operations.
+#[derive(Clone, Copy, Debug)]
+pub struct Step {
+    pub t: usize,
+    /// Oni left on the board (X_t).
+    pub oni: usize,
+    /// Fuku pushed off the board (Y_t).
+    pub lost_fuku: usize,
+    /// Score if the output stopped after `t` operations.
+    pub score: i64,
+}
+
+/// Returns one `Step` for each t = 0, ..., out.len(), stopping early at an invalid operation.
+pub fn compute_score_timeline(input: &Input, out: &[(char, usize)]) -> (Vec<Step>, String) {
+    let n = input.cs.len();
+    let mut cs = input.cs.clone();
+    let mut X = input.cs.iter().flatten().filter(|&&c| c == 'x').count();
+    let mut Y = 0;
+    let mut timeline = vec![Step {
+        t: 0,
+        oni: X,
+        lost_fuku: Y,
+        score: score_of(n, 0, X, Y),
+    }];
+    for (t, &(d, p)) in out.iter().enumerate() {
+        match apply_operation(&mut cs, d, p) {
+            Ok('x') => X -= 1,
+            Ok('o') => Y += 1,
+            Ok(_) => (),
+            Err(err) => return (timeline, err),
+        }
+        timeline.push(Step {
+            t: t + 1,
+            oni: X,
+            lost_fuku: Y,
+            score: score_of(n, t + 1, X, Y),
+        });
+    }
+    (timeline, String::new())
+}
+
+/// Line chart of the score over time.
+pub fn vis_timeline(timeline: &[Step]) -> String {
+    let (W, H) = (600, 200);
+    let max_t = timeline.last().map_or(0, |s| s.t).max(1);
+    // Scores go below 0 when many pieces are left, so the y range always includes both 0 and the lowest score.
+    let min_score = timeline.iter().map(|s| s.score).min().unwrap_or(0).min(0);
+    let max_score = timeline.iter().map(|s| s.score).max().unwrap_or(0).max(min_score + 1);
+    let y = |score: i64| H as i64 - (score - min_score) * H as i64 / (max_score - min_score);
+    let points = timeline
+        .iter()
+        .map(|s| format!("{},{}", s.t * W / max_t, y(s.score)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let best = timeline.iter().max_by_key(|s| (s.score, std::cmp::Reverse(s.t)));
+    let mut doc = svg::Document::new()
+        .set("id", "timeline")
+        .set("viewBox", (-50, -20, W + 70, H + 50))
+        .set("width", W + 70)
+        .set("height", H + 50)
+        .set("style", "background-color:white");
+    doc = doc.add(Style::new(
+        "text {text-anchor: middle;dominant-baseline: central;font-size: 12px;}",
+    ));
+    doc = doc.add(rect(0, 0, W, H, "#f8f8f8").set("stroke", "black"));
+    doc = doc.add(
+        Polyline::new()
+            .set("points", points)
+            .set("fill", "none")
+            .set("stroke", "#1e90ff")
+            .set("stroke-width", 2),
+    );
+    if min_score < 0 {
+        doc = doc
+            .add(
+                Polyline::new()
+                    .set("points", format!("0,{} {},{}", y(0), W, y(0)))
+                    .set("stroke", "gray")
+                    .set("stroke-dasharray", 4),
+            )
+            .add(Text::new("0").set("x", -25).set("y", y(0)));
+    }
+    doc = doc
+        .add(Text::new(max_score.to_string()).set("x", -25).set("y", 0))
+        .add(Text::new(min_score.to_string()).set("x", -25).set("y", H))
+        .add(Text::new(max_t.to_string()).set("x", W).set("y", H + 15))
+        .add(Text::new("T").set("x", W / 2).set("y", H + 15));
+    if let Some(best) = best {
+        doc = doc.add(
+            group(format!("t = {}\nscore = {}", best.t, best.score)).add(
+                Circle::new()
+                    .set("cx", best.t * W / max_t)
+                    .set("cy", y(best.score))
+                    .set("r", 4)
+                    .set("fill", "red"),
+            ),
+        );
+    }
+    doc.to_string()
 }
 
 /// 0 <= val <= 1
@@ -248,6 +702,7 @@ pub fn group(title: String) -> Group {
     Group::new().add(Title::new(title))
 }
 
+#[allow(clippy::len_zero)]
 pub fn vis_default(input: &Input, out: &Output) -> (i64, String, String) {
     let (mut score, err, svg) = vis(input, &out.out, false);
     if err.len() > 0 {
@@ -256,6 +711,7 @@ pub fn vis_default(input: &Input, out: &Output) -> (i64, String, String) {
     (score, err, svg)
 }
 
+#[allow(clippy::redundant_static_lifetimes)]
 const IMAGES: [&'static str; 2] = [
     // https://dotown.maeda-design-room.net/1093/
     "data:image/png;charset=utf-8;base64,iVBORw0KGgoAAAANSUhEUgAAApQAAALQCAYAAAAuBuBGAAAOd0lEQVR4nO3YsY5dRx3A4XvRbZZmG1YKTdK4cBMqIhpbtFEegBZcIEFHm2rvreiCkEBCAmFFsojEM6TzdhG9ARe2K0tbbROXh4InSH7JnbNnvu8FZuY/e8/+NDsAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAD4hvazDWxZlhXsAvg2jqfTbD/gId/o4/X1iGVhU/b7uRLrByvYAwAA95igBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkB+NjS46n05DTHK+vx6x7Oi1DFh5nP9Nhj787jrnfu+OQZXeXy1T3++Tx1ZB1D68+HrIu2+aFEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASA7Gx5Ycr6/dJ3AvPH1+O9VFPXl8NWTdw6uPh6w7Gy+UAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAcjA+tuR4Oi0zXejrL/+0gl2c1VT3++TLH61gF+d0NdX97na7/YhFnz6/HbEsG+eFEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASA7GBwB8354+v51qxn/bP1vBLs7HCyUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJDsZxvfsiwr2MX5HE+nqQ787C9/XsEugG/j8YO5vs+zefr8dqrm2O/nSiwvlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQHIwPuCburm8GDKzR3fv3NUZjLrfT3dfD1kX6LxQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAyWG28R1Pp2UF2zib33zx90lO+n/P1rAJAJiMF0oAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACA5GB9bcnN5sR9xnEd374ZM8ebyYhmy8CA3lxczHRfg3vBCCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIcJx7dfwR7OaZnnqOO8fPPfMXP+/Kt1DGDrfvnRkAO+/fAnM0159/vbH65gF2c15P/Rey9eDznsk8dXQ9blPLxQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAycH44DuxHzTGxfWdwedfbf6IAIUXSgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAINnPNr5lWVawi/N5+/CDuQ4MACvw43+/maqxvFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAcjC+zdvPPoCNW2YfANxjs32ffa82zAslAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQHIwP7q9Hd++mur2XL25XsAv4brz92fsmyWZ4oQQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAAJKD8W3bey9eT3Xez3710xXs4nxe/vFfsxyVCTx4eDXVNd9cjln3zT9/PWTd93/x1/2QhTkLL5QAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEByMD7gvnjw8MpdncHLF7dTrTvKqL/nf6zh8GyOF0oAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACDZzza+ZVlWsAu+L28ffuCCt23IN+vR3buJRjzOzeWF3++2Dfn9/ucPnw4Z6s8/+e2QdUfxQgkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAACJoAQAIBGUAAAkghIAgERQAgCQCEoAABJBCQBAIigBAEgEJQAAiaAEACARlAAAJIISAIBEUAIAkAhKAAASQQkAQCIoAQBIBCUAAImgBAAgEZQAACSCEgCARFACAJAISgAAEkEJAEAiKAEASAQlAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABwPrvd7n/ev3+4ZeU4igAAAABJRU5ErkJggg==",
@@ -263,12 +719,100 @@ const IMAGES: [&'static str; 2] = [
     "data:image/png;charset=utf-8;base64,iVBORw0KGgoAAAANSUhEUgAAA8AAAALQBAMAAACtWQSHAAAAJ1BMVEUAAAD///8WiokjGBVuTV9xTVWRYDSaZ6DCchPknSPo0mLqVRT/9uNxzgU8AAAAAnRSTlMAAHaTzTgAAASWSURBVHja7d1RUcNAEIDhwpyBaAkSQAJIAAmthGIBCSAhtRALZyEWcLB92Lm5a/J9rztJ0/y9vu7pBAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAOzZUzyehnzoa7M7XxLX/iau/Uhcu4XTZ7/xfRNYYARGYARGYARGYIERGIERGIERGIEFRmAERmAERmAERmCBERiBERiBERiBBUZgBEZgBEZgBBYYgREYgREYgREYgQVGYARGYARGYAQWGIERGIERGIG5b9DdhdchX1a82XBJ3LmG069wanehv2gERmAERmAERmAEFhiBERiBERiBEVhgBEZgBEZgBEZggb0CgREYgREYgREYgQVGYARGYARGYAQWGIERGIERGIERGIEFRmAERmAERmAEFhiBERiBERiBCZV4fE7c+vtgrzLeMFidYARGYAQWGIERGIERGIERWGAERmAERmAERmAEFhiBERiBERiBEVhgBEZgBEZgBEZggREYgREYgREYgRFYYARGYARGYARGYIERGIERGIERGIERWGAERmD6uLO7MN4/mNls+J64dg2nc2Lay5K49sUJ9heNwAiMwAiMwAiMwAIjMAIjMAIjMAILjMAIjMAIjMAILDACIzACIzACIzACC4zACIzACIzACCwwAiMwAiMwAiMwAguMwAiMwAiMwAgsMAIjMAIjMAITKb0++C+ctts/mNl7uDjBCIzACIzACCwwAiMwAiMwAiOwwAiMwAiMwAiMwAIjMAIjMAIjMAIjsMDsQRnzseZmd16dYARGYARGYARGYIERGIERGIERGIERWGAERmAERmAERmCBERiBERiBERiBj6484kNv4XQa8pnfnGAERmAEFhiBERiBERiBEVhgBEZgBEZgBEZgBBYYgREYgREYgRFYYARGYARGYARGYIERGIERGIERGIERWGAERmAERmAERmCBERiBERiBERiBEVhgBEZg+kjtLoy3BH42e+jMdsKamMZ+nGAERmAERmCBERiBERiBERiBBUZgBEZgBEZgBEZggREYgREYgREYgQVGYARGYARGYAQWGIERGIERGIERGIEFRmAERmAERmAEFhiBERiBERiBEVhgBEZgRpTaXbiF0zF3+d0S3+gcTqfEtDrBCIzAAiMwAiMwAiMwAguMwAiMwAiMwAiMwAIjMAIjMAIjMAILjMAIjMAIjMAIjMACIzACIzACIzACC4zACIzACIzACCwwAiMwAiMwAiMwAguMwAiMwLRSjvaFX8NpvG/xkvjczQlGYARGYIERGIERGIERGIEFRmAERmAERmAERmCBERiBERiBERiBBUZgBEZgBEZgBBYYgREYgREYgREYgQVGYARGYARGYAQWGIERGIERGIERGIEFRmAEpo9uuwvjHYI1nN4SnzsnpqsTjMAIjMAIjMACIzACIzACIzACC4zACIzACIzACCwwAiMwAiMwAiMwAguMwAiMwAiMwAgsMAIjMAIjMAIjsMBegcAIjMAIjMAIjMACIzACIzACIzACC4zAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIzlH8HCI23db016AAAAAElFTkSuQmCC",
 ];
 
+/// A piece pushed off the board by the `t`-th operation (1-indexed).
+#[derive(Clone, Copy, Debug)]
+pub struct Fallen {
+    pub t: usize,
+    pub d: char,
+    pub p: usize,
+    /// 'x' for oni, 'o' for fuku.
+    pub piece: char,
+}
+
+/// Returns every piece pushed off the board, stopping at the first invalid operation.
+pub fn fallen_pieces(input: &Input, out: &[(char, usize)]) -> Vec<Fallen> {
+    let mut cs = input.cs.clone();
+    let mut fallen = vec![];
+    for (t, &(d, p)) in out.iter().enumerate() {
+        match apply_operation(&mut cs, d, p) {
+            Ok('.') => (),
+            Ok(piece) => fallen.push(Fallen { t: t + 1, d, p, piece }),
+            Err(_) => break,
+        }
+    }
+    fallen
+}
+
+const PANEL_W: usize = 280;
+const ICON: usize = 16;
+/// Edges of the board and the direction that pushes pieces off over them.
+const EDGES: [(char, &str); 4] = [('L', "Left"), ('R', "Right"), ('U', "Top"), ('D', "Bottom")];
+
+/// Side panel with T, X, Y, the score and the pieces that fell off, grouped by edge.
+/// Returns the panel and its height.
+fn vis_panel(input: &Input, out: &[(char, usize)], x: usize, score: i64, err: &str) -> (Group, usize) {
+    let n = input.cs.len();
+    let (timeline, _) = compute_score_timeline(input, out);
+    let last = timeline.last().unwrap();
+    let fallen = fallen_pieces(input, out);
+    let D = 600 / n;
+    let text = |s: String, y: usize| Text::new(s).set("x", x).set("y", y).set("text-anchor", "start");
+    let mut g = Group::new().set("id", "panel");
+    let mut y = 10;
+    for (id, s) in [
+        ("panel_T", format!("T = {}", last.t)),
+        ("panel_X", format!("X (oni left) = {}", last.oni)),
+        ("panel_Y", format!("Y (fuku lost) = {}", last.lost_fuku)),
+        ("panel_score", format!("Score = {}", if err.is_empty() { score } else { 0 })),
+    ] {
+        g = g.add(text(s, y).set("id", id));
+        y += 24;
+    }
+    if !err.is_empty() {
+        g = g.add(text(err.to_owned(), y).set("fill", "red"));
+        y += 24;
+    }
+    let per_row = PANEL_W / (ICON + 2);
+    for (d, name) in EDGES {
+        let pieces = fallen.iter().filter(|f| f.d == d).collect::<Vec<_>>();
+        let oni = pieces.iter().filter(|f| f.piece == 'x').count();
+        y += 8;
+        g = g.add(text(format!("{}: {} oni, {} fuku", name, oni, pieces.len() - oni), y).set("id", format!("panel_{}", d)));
+        y += 14;
+        for (k, f) in pieces.iter().enumerate() {
+            let (ix, iy) = (x + k % per_row * (ICON + 2), y + k / per_row * (ICON + 2));
+            g = g.add(
+                group(format!(
+                    "t = {} ({} {})\n{}",
+                    f.t,
+                    f.d,
+                    f.p,
+                    if f.piece == 'x' { "Oni" } else { "Fukunokami" }
+                ))
+                .set("data-t", f.t)
+                .set("data-d", f.d.to_string())
+                .set("data-piece", f.piece.to_string())
+                .add(
+                    Use::new()
+                        .set("href", if f.piece == 'x' { "#oni" } else { "#fuku" })
+                        .set("transform", format!("translate({},{}) scale({})", ix, iy, ICON as f64 / D as f64)),
+                ),
+            );
+        }
+        y += (pieces.len() + per_row - 1) / per_row * (ICON + 2);
+    }
+    (g, y)
+}
+
+#[allow(clippy::len_zero, clippy::needless_borrow, clippy::needless_range_loop, clippy::useless_format)]
 pub fn vis(input: &Input, out: &[(char, usize)], manual: bool) -> (i64, String, String) {
     let n = input.cs.len();
     let D = 600 / n;
-    let W = D * n + if manual { 100 } else { 0 };
-    let H = D * n + if manual { 100 } else { 0 };
     let (score, err, cs) = compute_score_details(input, &out);
+    // The static page gets a side panel; the manual page draws its own panel in HTML.
+    let panel = if manual { None } else { Some(vis_panel(input, out, D * n + 20, score, &err)) };
+    let W = D * n + if manual { 100 } else { 20 + PANEL_W };
+    let H = (D * n + if manual { 100 } else { 0 }).max(panel.as_ref().map_or(0, |p| p.1));
     let mut doc = svg::Document::new()
         .set("id", "vis")
         .set("viewBox", (-5, -5, W + 10, H + 10))
@@ -333,6 +877,7 @@ pub fn vis(input: &Input, out: &[(char, usize)], manual: bool) -> (i64, String,
                         "#f0f0f0"
                     },
                 )
+                .set("id", format!("cell_{}_{}", i, j))
                 .set("stroke", "black")
                 .set("stroke-width", 1),
             );
@@ -354,6 +899,9 @@ pub fn vis(input: &Input, out: &[(char, usize)], manual: bool) -> (i64, String,
             doc = doc.add(g);
         }
     }
+    if let Some((panel, _)) = panel {
+        doc = doc.add(panel);
+    }
     if manual {
         for i in 0..n {
             doc = doc.add(
@@ -392,3 +940,449 @@ pub fn vis(input: &Input, out: &[(char, usize)], manual: bool) -> (i64, String,
     }
     (score, err, doc.to_string())
 }
+
+/// Builds a self-contained HTML page for manual play.
+/// `out` is replayed as the initial move list and can be undone like any other move.
+pub fn vis_manual(input: &Input, out: &[(char, usize)]) -> String {
+    let (_, _, svg) = vis(input, &[], true);
+    let board = input
+        .cs
+        .iter()
+        .map(|cs| format!("\"{}\"", cs.iter().collect::<String>()))
+        .collect::<Vec<_>>()
+        .join(",");
+    let moves = out
+        .iter()
+        .map(|&(d, p)| format!("[\"{}\",{}]", d, p))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        r##"<html>
+<head>
+<meta charset="utf-8">
+<title>AHC042 manual play</title>
+<style>
+body {{font-family: sans-serif;}}
+#vis text {{cursor: pointer; user-select: none;}}
+#vis text:hover {{fill: black;}}
+#panel {{display: inline-block; vertical-align: top; margin-left: 20px;}}
+#panel td {{padding: 0 8px;}}
+</style>
+</head>
+<body>
+<div style="display: inline-block">{}</div>
+<div id="panel">
+<table>
+<tr><td>T</td><td id="turn"></td></tr>
+<tr><td>X (oni left)</td><td id="oni"></td></tr>
+<tr><td>Y (fuku lost)</td><td id="fuku"></td></tr>
+<tr><td>Score</td><td id="score"></td></tr>
+</table>
+<div id="fallen"></div>
+<p id="error" style="color: red"></p>
+<p>
+<button onclick="manual_undo()">Undo (Ctrl+Z)</button>
+<button onclick="manual_reset()">Reset</button>
+</p>
+<p>
+<button onclick="manual_copy()">Copy</button>
+<button onclick="manual_download()">Download</button>
+</p>
+<textarea id="output" rows="20" cols="12" readonly></textarea>
+</div>
+<script>
+const BOARD = [{}];
+const MOVES = [{}];
+{}{}
+</script>
+</body>
+</html>
+"##,
+        svg, board, moves, SIMULATE_SCRIPT, MANUAL_SCRIPT
+    )
+}
+
+/// Slider under the `vis` SVG that shows the board and the side panel after each step of `timeline`.
+/// `timeline` comes from `compute_score_timeline(input, out)`; returns an empty string if it is empty.
+pub fn vis_steps(input: &Input, out: &[(char, usize)], timeline: &[Step]) -> String {
+    if timeline.is_empty() {
+        return String::new();
+    }
+    let T = timeline.len() - 1;
+    let board = input
+        .cs
+        .iter()
+        .map(|cs| format!("\"{}\"", cs.iter().collect::<String>()))
+        .collect::<Vec<_>>()
+        .join(",");
+    let moves = out[..T]
+        .iter()
+        .map(|&(d, p)| format!("[\"{}\",{}]", d, p))
+        .collect::<Vec<_>>()
+        .join(",");
+    let steps = timeline
+        .iter()
+        .map(|s| format!("[{},{},{},{}]", s.t, s.oni, s.lost_fuku, s.score))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        r##"<p>
+<button id="step_play" onclick="play_steps()">Play</button>
+<input type="range" id="step" min="0" max="{T}" value="{T}" style="width: 480px" oninput="show_step(this.value)">
+<span id="step_label">{T} / {T}</span>
+</p>
+<script>
+const BOARD = [{board}];
+const MOVES = [{moves}];
+// [t, X, Y, score] for each step
+const TIMELINE = [{steps}];
+{simulate}{script}
+</script>
+"##,
+        T = T,
+        board = board,
+        moves = moves,
+        steps = steps,
+        simulate = SIMULATE_SCRIPT,
+        script = STEP_SCRIPT,
+    )
+}
+
+const STEP_SCRIPT: &str = r##"
+// Redraws the board and the side panel of the static view after the first t moves.
+function show_step(t) {
+    t = Number(t);
+    const [cs] = simulate(MOVES.slice(0, t));
+    const svg = document.getElementById("vis");
+    svg.querySelectorAll(":scope > g:not(#panel) > use, :scope > use").forEach(e => e.remove());
+    const last = t > 0 ? MOVES[t - 1] : null;
+    for (let i = 0; i < N; i++) {
+        for (let j = 0; j < N; j++) {
+            const hit = last != null && (("LR".includes(last[0]) && last[1] == i) || ("UD".includes(last[0]) && last[1] == j));
+            document.getElementById("cell_" + i + "_" + j).setAttribute("fill", hit ? "#f0d0d0" : "#f0f0f0");
+            if (cs[i][j] == ".") continue;
+            const use = document.createElementNS(SVG_NS, "use");
+            use.setAttribute("href", cs[i][j] == "x" ? "#oni" : "#fuku");
+            use.setAttribute("x", j * D);
+            use.setAttribute("y", i * D);
+            svg.appendChild(use);
+        }
+    }
+    const [T, X, Y, score] = TIMELINE[t];
+    document.getElementById("panel_T").textContent = "T = " + T;
+    document.getElementById("panel_X").textContent = "X (oni left) = " + X;
+    document.getElementById("panel_Y").textContent = "Y (fuku lost) = " + Y;
+    document.getElementById("panel_score").textContent = "Score = " + score;
+    for (const [d, name] of [["L", "Left"], ["R", "Right"], ["U", "Top"], ["D", "Bottom"]]) {
+        let oni = 0;
+        let fuku = 0;
+        for (const icon of svg.querySelectorAll('#panel [data-d="' + d + '"]')) {
+            const shown = Number(icon.dataset.t) <= t;
+            icon.style.display = shown ? "" : "none";
+            if (shown && icon.dataset.piece == "x") oni++;
+            if (shown && icon.dataset.piece == "o") fuku++;
+        }
+        document.getElementById("panel_" + d).textContent = name + ": " + oni + " oni, " + fuku + " fuku";
+    }
+    document.getElementById("step").value = t;
+    document.getElementById("step_label").textContent = t + " / " + (TIMELINE.length - 1);
+}
+
+let step_timer = null;
+
+// Steps through the moves from the start, or stops if already playing.
+function play_steps() {
+    const button = document.getElementById("step_play");
+    if (step_timer != null) {
+        clearInterval(step_timer);
+        step_timer = null;
+        button.textContent = "Play";
+        return;
+    }
+    const slider = document.getElementById("step");
+    let t = Number(slider.value) >= TIMELINE.length - 1 ? 0 : Number(slider.value);
+    button.textContent = "Stop";
+    show_step(t);
+    step_timer = setInterval(() => {
+        if (t >= TIMELINE.length - 1) {
+            play_steps();
+            return;
+        }
+        show_step(++t);
+    }, Math.max(10, 5000 / TIMELINE.length));
+}
+"##;
+
+/// Replays moves on `BOARD` in the browser; shared by the manual page and the step slider.
+const SIMULATE_SCRIPT: &str = r##"
+const N = BOARD.length;
+const D = Math.floor(600 / N);
+const SVG_NS = "http://www.w3.org/2000/svg";
+
+// Returns the board after `ops` and the pieces pushed off it as [t, d, p, piece].
+function simulate(ops) {
+    const cs = BOARD.map(row => row.split(""));
+    const fallen = [];
+    for (const [t, [d, p]] of ops.entries()) {
+        const piece = d == "L" ? cs[p][0] : d == "R" ? cs[p][N - 1] : d == "U" ? cs[0][p] : cs[N - 1][p];
+        if (piece != ".") fallen.push([t + 1, d, p, piece]);
+        if (d == "L") {
+            for (let j = 0; j < N - 1; j++) cs[p][j] = cs[p][j + 1];
+            cs[p][N - 1] = ".";
+        } else if (d == "R") {
+            for (let j = N - 1; j > 0; j--) cs[p][j] = cs[p][j - 1];
+            cs[p][0] = ".";
+        } else if (d == "U") {
+            for (let i = 0; i < N - 1; i++) cs[i][p] = cs[i + 1][p];
+            cs[N - 1][p] = ".";
+        } else if (d == "D") {
+            for (let i = N - 1; i > 0; i--) cs[i][p] = cs[i - 1][p];
+            cs[0][p] = ".";
+        }
+    }
+    return [cs, fallen];
+}
+"##;
+
+const MANUAL_SCRIPT: &str = r##"
+const NUM_FUKU = BOARD.join("").split("").filter(c => c == "o").length;
+let ops = MOVES.slice();
+
+function render() {
+    const [cs, fallen] = simulate(ops);
+    const svg = document.getElementById("vis");
+    svg.querySelectorAll("use").forEach(e => e.remove());
+    const last = ops.length > 0 ? ops[ops.length - 1] : null;
+    let X = 0;
+    let Y = NUM_FUKU;
+    for (let i = 0; i < N; i++) {
+        for (let j = 0; j < N; j++) {
+            const hit = last != null && (("LR".includes(last[0]) && last[1] == i) || ("UD".includes(last[0]) && last[1] == j));
+            document.getElementById("cell_" + i + "_" + j).setAttribute("fill", hit ? "#f0d0d0" : "#f0f0f0");
+            if (cs[i][j] == ".") continue;
+            if (cs[i][j] == "x") X++;
+            if (cs[i][j] == "o") Y--;
+            const use = document.createElementNS(SVG_NS, "use");
+            use.setAttribute("href", cs[i][j] == "x" ? "#oni" : "#fuku");
+            use.setAttribute("x", j * D);
+            use.setAttribute("y", i * D);
+            use.setAttribute("pointer-events", "none");
+            svg.appendChild(use);
+        }
+    }
+    const T = ops.length;
+    const score = X == 0 && Y == 0 ? 8 * N * N - T : 4 * N * N - N * (X + Y);
+    document.getElementById("turn").textContent = T;
+    document.getElementById("oni").textContent = X;
+    document.getElementById("fuku").textContent = Y;
+    document.getElementById("score").textContent = score;
+    document.getElementById("error").textContent = T > 4 * N * N ? "Too many operations" : "";
+    const edges = document.getElementById("fallen");
+    edges.replaceChildren();
+    for (const [d, name] of [["L", "Left"], ["R", "Right"], ["U", "Top"], ["D", "Bottom"]]) {
+        const pieces = fallen.filter(f => f[1] == d);
+        const oni = pieces.filter(f => f[3] == "x").length;
+        const div = document.createElement("div");
+        div.textContent = name + ": " + oni + " oni, " + (pieces.length - oni) + " fuku ";
+        for (const [t, , p, piece] of pieces) {
+            const icon = document.createElementNS(SVG_NS, "svg");
+            icon.setAttribute("width", 16);
+            icon.setAttribute("height", 16);
+            const title = document.createElementNS(SVG_NS, "title");
+            title.textContent = "t = " + t + " (" + d + " " + p + ")";
+            const use = document.createElementNS(SVG_NS, "use");
+            use.setAttribute("href", piece == "x" ? "#oni" : "#fuku");
+            use.setAttribute("transform", "scale(" + 16 / D + ")");
+            icon.append(title, use);
+            div.appendChild(icon);
+        }
+        edges.appendChild(div);
+    }
+    document.getElementById("output").value = ops.map(([d, p]) => d + " " + p).join("\n");
+}
+
+function manual_update(d, p) {
+    ops.push([d, p]);
+    render();
+}
+
+function manual_undo() {
+    ops.pop();
+    render();
+}
+
+function manual_reset() {
+    ops = [];
+    render();
+}
+
+function manual_copy() {
+    navigator.clipboard.writeText(document.getElementById("output").value + "\n");
+}
+
+function manual_download() {
+    const blob = new Blob([document.getElementById("output").value + "\n"], { type: "text/plain" });
+    const a = document.createElement("a");
+    a.href = URL.createObjectURL(blob);
+    a.download = "out.txt";
+    a.click();
+    URL.revokeObjectURL(a.href);
+}
+
+document.addEventListener("keydown", e => {
+    if ((e.ctrlKey || e.metaKey) && e.key == "z") {
+        e.preventDefault();
+        manual_undo();
+    }
+});
+
+render();
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input() -> Input {
+        parse_input("3\nx.o\no.x\n.xo\n").unwrap()
+    }
+
+    #[test]
+    fn parse_output_errors() {
+        let errs = parse_output_all(&input(), "L 0\nL 9\nQ 1\nR\n").err().unwrap();
+        assert_eq!(
+            errs,
+            vec![
+                "Operation 2 (line 2): Out of range: 9",
+                "Operation 3 (line 3): Invalid direction: Q",
+                "Operation 4 (line 4): Unexpected EOF",
+            ]
+        );
+        assert_eq!(
+            parse_output(&input(), "L 0\nL 9\nQ 1\n").err().unwrap(),
+            "Operation 2 (line 2): Out of range: 9"
+        );
+    }
+
+    #[test]
+    fn parse_output_error_line_of_direction() {
+        // The index of operation 2 is on line 3, but the error points at line 2 where the operation starts.
+        assert_eq!(
+            parse_output(&input(), "L 0\nU\n9\n").err().unwrap(),
+            "Operation 2 (line 2): Out of range: 9"
+        );
+    }
+
+    fn count(input: &Input, c: char) -> usize {
+        input.cs.iter().flatten().filter(|&&d| d == c).count()
+    }
+
+    #[test]
+    fn gen_with_params() {
+        // The default parameters reproduce the contest generator, which made ../input.
+        let expected = parse_input(include_str!("../../input/0000.txt")).unwrap();
+        assert_eq!(gen_with(0, &GenParams::default()).unwrap().cs, expected.cs);
+        assert_eq!(gen(0).cs, expected.cs);
+
+        for seed in 0..20 {
+            let params = GenParams { num_fuku: 24, num_oni: 8, ..GenParams::new(8) };
+            let input = gen_with(seed, &params).unwrap();
+            assert_eq!(input.cs.len(), 8);
+            assert!(input.cs.iter().all(|row| row.len() == 8));
+            assert_eq!(count(&input, 'o'), 24);
+            assert_eq!(count(&input, 'x'), 8);
+            assert!(is_guaranteed(&input));
+        }
+        // Without the guarantee, crowded boards have oni that cannot escape.
+        let params = GenParams { num_fuku: 24, num_oni: 8, guarantee: false, ..GenParams::new(8) };
+        assert!((0..20).any(|seed| !is_guaranteed(&gen_with(seed, &params).unwrap())));
+        assert!(gen_with(0, &GenParams { num_fuku: 40, num_oni: 30, ..GenParams::new(8) }).is_err());
+    }
+
+    #[test]
+    fn gen_hard_never_easier() {
+        let params = GenParams::new(6);
+        let n = params.n;
+        // A stand-in solver that needs one operation per cell between each oni and its nearest edge.
+        let ops = |input: &Input| {
+            let mut ops = 0;
+            for i in 0..n {
+                for j in 0..n {
+                    if input.cs[i][j] == 'x' {
+                        ops += (i + 1).min(n - i).min(j + 1).min(n - j);
+                    }
+                }
+            }
+            ops as i64
+        };
+        let eval = |input: &Input| Some((8 * n * n) as i64 - ops(input));
+        for objective in [Objective::Ops, Objective::Gap] {
+            let badness = |input: &Input| match objective {
+                Objective::Ops => ops(input),
+                Objective::Gap => ops(input) - lower_bound(input) as i64,
+            };
+            for seed in 0..5 {
+                let initial = gen_with(seed, &params).unwrap();
+                let (hard, bad) = gen_hard(seed, &params, 100, objective, eval).unwrap();
+                assert_eq!(bad, badness(&hard));
+                assert!(bad >= badness(&initial));
+                assert!(is_guaranteed(&hard));
+                assert_eq!(count(&hard, 'x'), count(&initial, 'x'));
+                assert_eq!(count(&hard, 'o'), count(&initial, 'o'));
+            }
+        }
+    }
+
+    #[test]
+    fn features_small_board() {
+        let input = parse_input("4\nx..x\n.o..\n..x.\no...\n").unwrap();
+        let f = features(&input);
+        // (0, 0) escapes up, left and right; (0, 3) in all four directions; (2, 2) in all four directions.
+        assert_eq!((f.escape_up, f.escape_down, f.escape_left, f.escape_right), (3, 2, 3, 3));
+        // Rows 0 and 2, columns 2 and 3 have no fuku.
+        assert_eq!((f.free_rows, f.free_cols), (2, 2));
+        // Shortest escapes: 1, 1 and 2.
+        assert!((f.avg_escape_dist - 4.0 / 3.0).abs() < 1e-9);
+        // The two oni in row 0 share it.
+        assert_eq!(f.shared_oni, 2);
+    }
+
+    #[test]
+    fn score_timeline_matches_details() {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        for seed in 0..10 {
+            let input = gen_with(seed, &GenParams::new(6)).unwrap();
+            // Random operations drop fuku as well as oni.
+            let out = (0..rng.gen_range(0..100))
+                .map(|_| (['L', 'R', 'U', 'D'][rng.gen_range(0..4)], rng.gen_range(0..6)))
+                .collect::<Vec<_>>();
+            let (timeline, err) = compute_score_timeline(&input, &out);
+            assert!(err.is_empty());
+            assert_eq!(timeline.len(), out.len() + 1);
+            let (score, _, cs) = compute_score_details(&input, &out);
+            let last = timeline.last().unwrap();
+            assert_eq!(last.score, score);
+            assert_eq!(last.oni, cs.iter().flatten().filter(|&&c| c == 'x').count());
+            assert_eq!(last.lost_fuku, count(&input, 'o') - cs.iter().flatten().filter(|&&c| c == 'o').count());
+            for w in timeline.windows(2) {
+                assert_eq!(w[1].t, w[0].t + 1);
+                assert!(w[1].oni <= w[0].oni);
+                assert!(w[1].lost_fuku >= w[0].lost_fuku);
+            }
+        }
+    }
+
+    #[test]
+    fn fallen_pieces_known_moves() {
+        let out = [('L', 0), ('R', 1), ('U', 1), ('D', 0), ('Q', 0), ('R', 2)];
+        let fallen = fallen_pieces(&input(), &out)
+            .iter()
+            .map(|f| (f.t, f.d, f.p, f.piece))
+            .collect::<Vec<_>>();
+        // x.o    .o.    .o.    .o.
+        // o.x -> o.x -> .o. -> .x.
+        // .xo    .xo    .xo    ..o
+        // D 0 pushes nothing off, and the invalid 5th operation stops the replay before R 2.
+        assert_eq!(fallen, vec![(1, 'L', 0, 'x'), (2, 'R', 1, 'x'), (3, 'U', 1, 'o')]);
+    }
+}